@@ -0,0 +1,73 @@
+//! Peak memory of `write_json`, compared to building the same JSON as a
+//! `serde_json::Value` before writing it.
+//!
+//! Run with `cargo bench --bench json`.
+
+use petgraph::graph::DiGraph;
+use petgraph::visit::EdgeRef;
+use serde_json::json;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::sink;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use taskdep::{write_json, Dep, Node};
+
+/// Counts the bytes allocated, and the most allocated at once
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current =
+            CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(current, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Bytes allocated by `f` on top of what was allocated before, at its peak
+fn peak<F: FnOnce()>(f: F) -> usize {
+    let before = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    f();
+    PEAK.load(Ordering::Relaxed) - before
+}
+
+fn main() {
+    let mut g = DiGraph::new();
+    let tasks = 100_000;
+    for i in 0..tasks {
+        let mut n = Node::new(format!("ns{}:task{i}", i % 100));
+        n.defined = true;
+        n.desc = Some(format!("Task number {i}"));
+        let idx = g.add_node(n);
+        if i > 0 {
+            g.add_edge(idx, (i / 2).into(), Dep::default());
+        }
+    }
+    let streaming = peak(|| write_json(&g, sink()).unwrap());
+    let in_memory = peak(|| {
+        let nodes = g
+            .node_weights()
+            .map(|n| json!({"name": n.name, "defined": n.defined, "desc": n.desc, "file": n.file}))
+            .collect::<Vec<_>>();
+        let edges = g
+            .edge_references()
+            .map(|e| json!({"from": g[e.source()].name, "to": g[e.target()].name}))
+            .collect::<Vec<_>>();
+        let value = json!({"nodes": nodes, "edges": edges});
+        serde_json::to_writer(sink(), &value).unwrap();
+    });
+    println!("{tasks} tasks, peak memory:");
+    println!("  write_json:         {:>12} bytes", streaming);
+    println!("  serde_json::Value:  {:>12} bytes", in_memory);
+}
@@ -0,0 +1,153 @@
+use crate::escape_xml;
+
+/// Separator of the ends of an edge in the titles Graphviz gives edges
+const EDGE: &str = "&#45;&gt;";
+
+/// Make an SVG rendered by Graphviz from a graph with the tasks `tasks`, as
+/// pairs of a name and a description, in the order of their indices, easier
+/// to read with a screen reader.
+///
+/// The root element gets `role="img"` and `label` as its `aria-label`, and
+/// starts with `label` as its `<title>` and `description` as its `<desc>`.
+/// Each task gets its name as the `<title>` of its group, instead of the
+/// index Graphviz writes there, followed by its description as a `<desc>`,
+/// and each dependency gets `DEP -> TASK`, which screen readers announce
+/// when moving to them. Titles that aren't an index of `tasks` are left
+/// alone.
+pub fn add_labels(
+    svg: &[u8],
+    label: &str,
+    description: &str,
+    tasks: &[(String, Option<String>)],
+) -> Vec<u8> {
+    let svg = String::from_utf8_lossy(svg);
+    let task = |index: &str| {
+        index
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| tasks.get(i))
+    };
+    let name = |index: &str| task(index).map(|(name, _)| name);
+    let mut result = String::with_capacity(svg.len());
+    let mut rest = svg.as_ref();
+    if let Some(start) = rest.find("<svg ") {
+        let start = start + "<svg ".len();
+        result.push_str(&rest[..start]);
+        result.push_str(&format!(
+            "role=\"img\" aria-label=\"{}\" ",
+            escape_xml(label)
+        ));
+        rest = &rest[start..];
+        if let Some(end) = rest.find('>').map(|i| i + 1) {
+            result.push_str(&rest[..end]);
+            result.push_str(&format!(
+                "\n<title>{}</title>\n<desc>{}</desc>",
+                escape_xml(label),
+                escape_xml(description)
+            ));
+            rest = &rest[end..];
+        }
+    }
+    while let Some(group) = rest.find("<g ") {
+        let Some(tag_end) = rest[group..].find('>').map(|i| group + i + 1)
+        else {
+            break;
+        };
+        let tag = &rest[group..tag_end];
+        result.push_str(&rest[..tag_end]);
+        rest = &rest[tag_end..];
+        // Followed by the classes of interactive clusters, if any
+        let is_node = tag.contains("class=\"node");
+        if !is_node && !tag.contains("class=\"edge") {
+            continue;
+        }
+        let body = rest.trim_start();
+        let Some(title) = body.strip_prefix("<title>") else {
+            continue;
+        };
+        let Some(end) = title.find("</title>") else {
+            continue;
+        };
+        let text = &title[..end];
+        let replaced = if is_node {
+            task(text).map(|(name, desc)| match desc {
+                Some(desc) => format!(
+                    "<title>{}</title>\n<desc>{}</desc>",
+                    escape_xml(name),
+                    escape_xml(desc)
+                ),
+                None => format!("<title>{}</title>", escape_xml(name)),
+            })
+        } else {
+            text.split_once(EDGE).and_then(|(from, to)| {
+                let (from, to) = (name(from)?, name(to)?);
+                Some(format!(
+                    "<title>{} -&gt; {}</title>",
+                    escape_xml(from),
+                    escape_xml(to)
+                ))
+            })
+        };
+        if let Some(replaced) = replaced {
+            let skipped = rest.len() - body.len();
+            result.push_str(&rest[..skipped]);
+            result.push_str(&replaced);
+            rest = &title[end + "</title>".len()..];
+        }
+    }
+    result.push_str(rest);
+    result.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::a11y::add_labels;
+    use indoc::indoc;
+
+    #[test]
+    fn test_add_labels() {
+        let svg = indoc! {r#"
+            <svg width="62pt" height="116pt">
+            <g id="graph0" class="graph">
+            <title>%3</title>
+            <g id="node1" class="node">
+            <title>0</title>
+            <text>lib:build</text>
+            </g>
+            <g id="edge1" class="edge taskdep-in-0">
+            <title>1&#45;&gt;0</title>
+            </g>
+            <g id="node9" class="node">
+            <title>9</title>
+            </g>
+            </g>
+            </svg>
+        "#};
+        let tasks = [
+            ("lib:build".to_string(), Some("Build \"lib\"".to_string())),
+            ("a<b".to_string(), None),
+        ];
+        let svg = add_labels(
+            svg.as_bytes(),
+            "Dependencies & more",
+            "2 tasks and 1 dependency",
+            &tasks,
+        );
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with(
+            "<svg role=\"img\" aria-label=\"Dependencies &amp; more\" width="
+        ));
+        assert!(svg.contains(
+            "height=\"116pt\">\n<title>Dependencies &amp; more</title>\n\
+             <desc>2 tasks and 1 dependency</desc>\n<g id=\"graph0\""
+        ));
+        assert!(svg.contains("<title>%3</title>"));
+        assert!(svg.contains(
+            "class=\"node\">\n<title>lib:build</title>\n\
+             <desc>Build &quot;lib&quot;</desc>\n<text>"
+        ));
+        assert!(svg.contains("<title>a&lt;b -&gt; lib:build</title>"));
+        assert!(svg.contains("<title>9</title>"));
+    }
+}
@@ -0,0 +1,1435 @@
+use crate::{Dep, Node};
+use petgraph::algo::{condensation, tarjan_scc};
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use regex::Regex;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// Strongly connected components that form a cycle: those with more than one
+/// task, or a single task depending on itself.
+pub fn cycles(g: &DiGraph<Node, Dep>) -> Vec<Vec<NodeIndex>> {
+    tarjan_scc(g)
+        .into_iter()
+        .filter(|c| c.len() > 1 || g.contains_edge(c[0], c[0]))
+        .collect()
+}
+
+/// Counts summarizing a graph
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Tasks defined in a Taskfile
+    pub tasks: usize,
+    pub dependencies: usize,
+    pub cycles: usize,
+    /// Tasks depended on without being defined anywhere
+    pub undefined: usize,
+}
+
+impl Stats {
+    pub fn new(g: &DiGraph<Node, Dep>) -> Self {
+        let tasks = g.node_weights().filter(|n| n.defined).count();
+        Stats {
+            tasks,
+            dependencies: g.edge_count(),
+            cycles: cycles(g).len(),
+            undefined: g.node_count() - tasks,
+        }
+    }
+}
+
+/// `12 tasks • 1 cycle`, with the number of undefined tasks when there are
+/// any
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = |n: usize, what: &str| match n {
+            1 => format!("1 {what}"),
+            n => format!("{n} {what}s"),
+        };
+        write!(
+            f,
+            "{} \u{2022} {}",
+            plural(self.tasks, "task"),
+            plural(self.cycles, "cycle")
+        )?;
+        if self.undefined > 0 {
+            write!(f, " \u{2022} {} undefined", self.undefined)?;
+        }
+        Ok(())
+    }
+}
+
+/// One of the shortest loops of dependencies through the first task of
+/// `cycle`, as returned by [`cycles`], from a dependency to the task
+/// depending on it: `[a, b, a]` when `b` depends on `a`, which depends on `b`.
+pub fn cycle_path(
+    g: &DiGraph<Node, Dep>,
+    cycle: &[NodeIndex],
+) -> Vec<NodeIndex> {
+    let start = cycle[0];
+    let mut previous = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(idx) = queue.pop_front() {
+        for next in g.neighbors(idx) {
+            if !cycle.contains(&next) || previous.contains_key(&next) {
+                continue;
+            }
+            previous.insert(next, idx);
+            if next == start {
+                let mut path = vec![start];
+                let mut at = idx;
+                while at != start {
+                    path.push(at);
+                    at = previous[&at];
+                }
+                path.push(start);
+                path.reverse();
+                return path;
+            }
+            queue.push_back(next);
+        }
+    }
+    vec![]
+}
+
+/// For each cycle, dependencies whose removal makes its tasks acyclic.
+///
+/// Finding the fewest such dependencies (a minimum feedback arc set) is
+/// NP-hard. Instead, this runs a depth-first search within each cycle and
+/// picks its back edges, the ones pointing to a task still being visited.
+/// Removing all the back edges of a depth-first search always leaves an
+/// acyclic graph, and often there is only one per cycle.
+pub fn feedback_edges(
+    g: &DiGraph<Node, Dep>,
+) -> Vec<(Vec<NodeIndex>, Vec<EdgeIndex>)> {
+    cycles(g)
+        .into_iter()
+        .map(|mut cycle| {
+            cycle.sort();
+            let members = cycle.iter().copied().collect::<HashSet<_>>();
+            let mut visited = HashSet::new();
+            let mut back_edges = vec![];
+            for &start in &cycle {
+                if !visited.contains(&start) {
+                    back_edges_from(
+                        g,
+                        start,
+                        &members,
+                        &mut visited,
+                        &mut vec![],
+                        &mut back_edges,
+                    );
+                }
+            }
+            (cycle, back_edges)
+        })
+        .collect()
+}
+
+fn back_edges_from(
+    g: &DiGraph<Node, Dep>,
+    idx: NodeIndex,
+    members: &HashSet<NodeIndex>,
+    visited: &mut HashSet<NodeIndex>,
+    stack: &mut Vec<NodeIndex>,
+    back_edges: &mut Vec<EdgeIndex>,
+) {
+    visited.insert(idx);
+    stack.push(idx);
+    let mut edges = g.edges(idx).collect::<Vec<_>>();
+    edges.sort_by_key(|e| e.target());
+    for e in edges {
+        let target = e.target();
+        if !members.contains(&target) {
+            continue;
+        }
+        if stack.contains(&target) {
+            back_edges.push(e.id());
+        } else if !visited.contains(&target) {
+            back_edges_from(g, target, members, visited, stack, back_edges);
+        }
+    }
+    stack.pop();
+}
+
+/// Dependencies implied by others: those from a dependency to a task that
+/// depends on it through other tasks anyway, with the shortest such path,
+/// from the dependency to the task. When Task runs the task, it runs the
+/// dependency before it all the same without them.
+///
+/// Cycles count as a single task: dependencies between the tasks of the same
+/// cycle are left out, as every one of them is implied by the others, and so
+/// are those implied only by another dependency between the same cycles.
+/// This way, removing all the dependencies found still leaves every task with
+/// the dependencies it had, directly or not.
+pub fn redundant_deps(
+    g: &DiGraph<Node, Dep>,
+) -> Vec<(EdgeIndex, Vec<NodeIndex>)> {
+    let mut component = HashMap::new();
+    for (i, scc) in tarjan_scc(g).into_iter().enumerate() {
+        for idx in scc {
+            component.insert(idx, i);
+        }
+    }
+    let mut found = vec![];
+    for e in g.edge_references() {
+        let (from, to) = (e.source(), e.target());
+        if component[&from] == component[&to] {
+            continue;
+        }
+        // Breadth-first from the dependency, without going straight from its
+        // cycle, if any, to the one of the task
+        let mut previous = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(idx) = queue.pop_front() {
+            if idx == to {
+                break;
+            }
+            for next in g.edges(idx) {
+                let direct = component[&idx] == component[&from]
+                    && component[&next.target()] == component[&to];
+                if !direct && !previous.contains_key(&next.target()) {
+                    previous.insert(next.target(), idx);
+                    queue.push_back(next.target());
+                }
+            }
+        }
+        if previous.contains_key(&to) {
+            let mut path = vec![to];
+            while path[path.len() - 1] != from {
+                path.push(previous[&path[path.len() - 1]]);
+            }
+            path.reverse();
+            found.push((e.id(), path));
+        }
+    }
+    found
+}
+
+/// Tasks defined with a `:` in their own name, which Task can't tell apart
+/// from a task of an included Taskfile: `docs:build` could be either.
+pub fn colon_names(g: &DiGraph<Node, Dep>) -> Vec<NodeIndex> {
+    g.node_indices()
+        .filter(|&idx| g[idx].defined && g[idx].local_name().contains(':'))
+        .collect()
+}
+
+/// Tasks that aren't defined but are named like a namespace, as when writing
+/// `docker` for `docker:build`, with the defined tasks of the namespace to
+/// suggest instead: those directly in it, or else all of them, by name.
+/// Namespaces with a `default` task are left out, as Task runs it for them.
+pub fn namespace_deps(g: &DiGraph<Node, Dep>) -> Vec<(NodeIndex, Vec<&str>)> {
+    let defined = g
+        .node_weights()
+        .filter(|n| n.defined)
+        .map(|n| n.name.as_str())
+        .collect::<Vec<_>>();
+    let mut found = vec![];
+    for idx in g.node_indices().filter(|&idx| !g[idx].defined) {
+        let namespace = g[idx].name.as_str();
+        let default = format!("{namespace}:default");
+        let mut tasks = defined
+            .iter()
+            .copied()
+            .filter(|name| in_namespace(name, namespace))
+            .collect::<Vec<_>>();
+        if tasks.is_empty() || tasks.contains(&default.as_str()) {
+            continue;
+        }
+        let direct = tasks
+            .iter()
+            .copied()
+            .filter(|name| !name[namespace.len() + 1..].contains(':'))
+            .collect::<Vec<_>>();
+        if !direct.is_empty() {
+            tasks = direct;
+        }
+        tasks.sort();
+        found.push((idx, tasks));
+    }
+    found
+}
+
+/// The shortest way the task `from` depends on the task `to`, directly or
+/// not: the tasks from `from` to `to`, each depending directly on the next.
+pub fn dependency_path(
+    g: &DiGraph<Node, Dep>,
+    from: NodeIndex,
+    to: NodeIndex,
+) -> Option<Vec<NodeIndex>> {
+    let mut previous = HashMap::from([(from, from)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(idx) = queue.pop_front() {
+        if idx == to {
+            let mut path = vec![to];
+            while path[path.len() - 1] != from {
+                path.push(previous[&path[path.len() - 1]]);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        let mut deps = g
+            .neighbors_directed(idx, Direction::Incoming)
+            .collect::<Vec<_>>();
+        // The same path whatever the order of the Taskfile
+        deps.sort_by_key(|&dep| &g[dep].name);
+        for dep in deps {
+            if let Entry::Vacant(e) = previous.entry(dep) {
+                e.insert(idx);
+                queue.push_back(dep);
+            }
+        }
+    }
+    None
+}
+
+/// The ways the task `from` depends on the task `to`, directly or not, as in
+/// [`dependency_path`], without going through a task twice: at most `max` of
+/// them, and whether that's all of them. They come in the order of the
+/// names of the tasks along them.
+pub fn dependency_paths(
+    g: &DiGraph<Node, Dep>,
+    from: NodeIndex,
+    to: NodeIndex,
+    max: usize,
+) -> (Vec<Vec<NodeIndex>>, bool) {
+    // Tasks depending on `to`, directly or not: the only ones worth going
+    // through
+    let mut towards = HashSet::from([to]);
+    let mut queue = VecDeque::from([to]);
+    while let Some(idx) = queue.pop_front() {
+        for task in g.neighbors(idx) {
+            if towards.insert(task) {
+                queue.push_back(task);
+            }
+        }
+    }
+    let mut paths = vec![];
+    let mut path = vec![from];
+    let complete = paths_from(g, to, &towards, max, &mut path, &mut paths);
+    (paths, complete)
+}
+
+/// Extend `path` towards `to` in every way, adding the paths found to
+/// `paths`, and return whether there were no more than `max` of them.
+fn paths_from(
+    g: &DiGraph<Node, Dep>,
+    to: NodeIndex,
+    towards: &HashSet<NodeIndex>,
+    max: usize,
+    path: &mut Vec<NodeIndex>,
+    paths: &mut Vec<Vec<NodeIndex>>,
+) -> bool {
+    let idx = path[path.len() - 1];
+    if idx == to {
+        if paths.len() == max {
+            return false;
+        }
+        paths.push(path.clone());
+        return true;
+    }
+    if !towards.contains(&idx) {
+        return true;
+    }
+    let mut deps = g
+        .neighbors_directed(idx, Direction::Incoming)
+        .collect::<Vec<_>>();
+    deps.sort_by_key(|&dep| &g[dep].name);
+    deps.dedup();
+    for dep in deps {
+        if path.contains(&dep) {
+            continue;
+        }
+        path.push(dep);
+        let complete = paths_from(g, to, towards, max, path, paths);
+        path.pop();
+        if !complete {
+            return false;
+        }
+    }
+    true
+}
+
+/// Each cycle of `g`, with the tasks outside of it that depend on it,
+/// directly or not, and that Task can't run until it is broken. Both are
+/// sorted by name, and the cycles blocking the most tasks come first.
+pub fn blocked_by_cycles(
+    g: &DiGraph<Node, Dep>,
+) -> Vec<(Vec<NodeIndex>, Vec<NodeIndex>)> {
+    let by_name = |tasks: &mut Vec<NodeIndex>| {
+        tasks.sort_by(|&a, &b| g[a].name.cmp(&g[b].name))
+    };
+    let mut blocked = cycles(g)
+        .into_iter()
+        .map(|mut cycle| {
+            let mut seen = cycle.iter().copied().collect::<HashSet<_>>();
+            let mut queue = cycle.iter().copied().collect::<VecDeque<_>>();
+            let mut tasks = vec![];
+            while let Some(idx) = queue.pop_front() {
+                for task in g.neighbors_directed(idx, Direction::Outgoing) {
+                    if seen.insert(task) {
+                        tasks.push(task);
+                        queue.push_back(task);
+                    }
+                }
+            }
+            by_name(&mut cycle);
+            by_name(&mut tasks);
+            (cycle, tasks)
+        })
+        .collect::<Vec<_>>();
+    blocked.sort_by(|(a, a_tasks), (b, b_tasks)| {
+        let names = |c: &[NodeIndex]| {
+            c.iter().map(|&idx| &g[idx].name).collect::<Vec<_>>()
+        };
+        b_tasks
+            .len()
+            .cmp(&a_tasks.len())
+            .then_with(|| names(a).cmp(&names(b)))
+    });
+    blocked
+}
+
+/// Tasks that some task of `roots` depends on, directly or not, and the
+/// roots themselves.
+pub fn reachable(
+    g: &DiGraph<Node, Dep>,
+    roots: &[NodeIndex],
+) -> HashSet<NodeIndex> {
+    let mut seen = roots.iter().copied().collect::<HashSet<_>>();
+    let mut queue = roots.iter().copied().collect::<VecDeque<_>>();
+    while let Some(idx) = queue.pop_front() {
+        for dep in g.neighbors_directed(idx, Direction::Incoming) {
+            if seen.insert(dep) {
+                queue.push_back(dep);
+            }
+        }
+    }
+    seen
+}
+
+/// The tasks Task runs for `target`: its dependencies, directly or not, and
+/// itself, in an order where each task comes after its dependencies. Ties
+/// are broken by name.
+///
+/// Fails with the tasks of the cycles among them, if any, since there is no
+/// such order then.
+pub fn plan(
+    g: &DiGraph<Node, Dep>,
+    target: NodeIndex,
+) -> Result<Vec<NodeIndex>, Vec<NodeIndex>> {
+    let tasks = reachable(g, &[target]);
+    let mut waiting = tasks
+        .iter()
+        .map(|&idx| {
+            let mut deps = g
+                .neighbors_directed(idx, Direction::Incoming)
+                .collect::<Vec<_>>();
+            deps.sort();
+            deps.dedup();
+            (idx, deps.len())
+        })
+        .collect::<HashMap<_, _>>();
+    let mut order = vec![];
+    loop {
+        let next = waiting
+            .iter()
+            .filter(|(_, &deps)| deps == 0)
+            .map(|(&idx, _)| idx)
+            .min_by_key(|&idx| &g[idx].name);
+        let Some(idx) = next else { break };
+        waiting.remove(&idx);
+        order.push(idx);
+        let mut dependents = g.neighbors(idx).collect::<Vec<_>>();
+        dependents.sort();
+        dependents.dedup();
+        for dependent in dependents {
+            if let Some(deps) = waiting.get_mut(&dependent) {
+                *deps -= 1;
+            }
+        }
+    }
+    if waiting.is_empty() {
+        Ok(order)
+    } else {
+        // The tasks left over are those of cycles, and those depending on them
+        let mut looping = cycles(g)
+            .into_iter()
+            .flatten()
+            .filter(|idx| waiting.contains_key(idx))
+            .collect::<Vec<_>>();
+        looping.sort_by_key(|&idx| &g[idx].name);
+        Err(looping)
+    }
+}
+
+/// Number of single-character insertions, deletions and substitutions
+/// turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Defined tasks with a name close to `name`, closest first, for suggestions
+/// when no task is called `name`: at most three, within one edit for every
+/// three characters or part of them.
+pub fn similar_names<'g>(
+    g: &'g DiGraph<Node, Dep>,
+    name: &str,
+) -> Vec<&'g str> {
+    let max = name.chars().count().div_ceil(3);
+    let mut similar = g
+        .node_weights()
+        .filter(|n| n.defined)
+        .map(|n| (edit_distance(name, &n.name), n.name.as_str()))
+        .filter(|&(d, _)| d <= max)
+        .collect::<Vec<_>>();
+    similar.sort();
+    similar.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+/// A task with the tasks it depends on and the tasks depending on it,
+/// directly or not, or only directly with `direct_only`.
+pub fn focus(
+    g: &DiGraph<Node, Dep>,
+    task: NodeIndex,
+    direct_only: bool,
+) -> HashSet<NodeIndex> {
+    let mut keep = HashSet::from([task]);
+    for direction in [Direction::Incoming, Direction::Outgoing] {
+        let mut queue = VecDeque::from([task]);
+        while let Some(idx) = queue.pop_front() {
+            for next in g.neighbors_directed(idx, direction) {
+                if keep.insert(next) && !direct_only {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    keep
+}
+
+/// The tasks whose full name, with its namespaces, doesn't match `pattern`
+/// anywhere.
+pub fn unmatched(
+    g: &DiGraph<Node, Dep>,
+    pattern: &Regex,
+) -> HashSet<NodeIndex> {
+    g.node_indices()
+        .filter(|&idx| !pattern.is_match(&g[idx].name))
+        .collect()
+}
+
+/// Whether the task `name` is in `namespace`, or in a namespace nested in it:
+/// `lib:docs:html` is in both `lib` and `lib:docs`.
+pub fn in_namespace(name: &str, namespace: &str) -> bool {
+    name.strip_prefix(namespace)
+        .is_some_and(|rest| rest.starts_with(':'))
+}
+
+/// The tasks that aren't in any of `namespaces`, by [`in_namespace`].
+pub fn outside_namespaces(
+    g: &DiGraph<Node, Dep>,
+    namespaces: &[String],
+) -> HashSet<NodeIndex> {
+    g.node_indices()
+        .filter(|&idx| {
+            !namespaces.iter().any(|ns| in_namespace(&g[idx].name, ns))
+        })
+        .collect()
+}
+
+/// The graph with only the tasks in `keep`, and the dependencies between
+/// them. Also returns where each kept task ended up.
+pub fn subgraph(
+    g: &DiGraph<Node, Dep>,
+    keep: &HashSet<NodeIndex>,
+) -> (DiGraph<Node, Dep>, HashMap<NodeIndex, NodeIndex>) {
+    let mut moved = HashMap::new();
+    let mut result = DiGraph::new();
+    for idx in g.node_indices().filter(|idx| keep.contains(idx)) {
+        moved.insert(idx, result.add_node(g[idx].clone()));
+    }
+    for e in g.edge_references() {
+        if let (Some(&from), Some(&to)) =
+            (moved.get(&e.source()), moved.get(&e.target()))
+        {
+            result.add_edge(from, to, *e.weight());
+        }
+    }
+    (result, moved)
+}
+
+/// The top-level namespace of a task: `a` for `a:b:build`, and `""` for
+/// tasks outside of any namespace.
+pub fn top_namespace(name: &str) -> &str {
+    name.split_once(':').map(|(ns, _)| ns).unwrap_or_default()
+}
+
+/// All the tasks in an order where each task comes after its dependencies,
+/// with the tasks of each cycle grouped together, sorted by name, since they
+/// can't come after one another. Other groups have a single task. Ties are
+/// broken by name, of the first task for a cycle.
+pub fn topo_groups(g: &DiGraph<Node, Dep>) -> Vec<Vec<NodeIndex>> {
+    let mut groups = tarjan_scc(g);
+    for group in &mut groups {
+        group.sort_by_key(|&idx| &g[idx].name);
+    }
+    let mut component = HashMap::new();
+    for (i, group) in groups.iter().enumerate() {
+        for &idx in group {
+            component.insert(idx, i);
+        }
+    }
+    // Dependencies of each group on other groups
+    let mut deps = vec![HashSet::new(); groups.len()];
+    for e in g.edge_references() {
+        let (from, to) = (component[&e.source()], component[&e.target()]);
+        if from != to {
+            deps[to].insert(from);
+        }
+    }
+    let mut order = vec![];
+    let mut done = HashSet::new();
+    while order.len() < groups.len() {
+        let next = (0..groups.len())
+            .filter(|i| !done.contains(i) && deps[*i].is_subset(&done))
+            .min_by_key(|&i| &g[groups[i][0]].name)
+            .unwrap_or_else(|| unreachable!("the groups don't form cycles"));
+        done.insert(next);
+        order.push(next);
+    }
+    order.into_iter().map(|i| groups[i].clone()).collect()
+}
+
+/// The tasks of each top-level namespace, by [`top_namespace`], together
+/// with the tasks of other namespaces they depend on or are dependencies of.
+pub fn namespace_pages(
+    g: &DiGraph<Node, Dep>,
+) -> BTreeMap<&str, HashSet<NodeIndex>> {
+    let mut pages = BTreeMap::<_, HashSet<_>>::new();
+    for idx in g.node_indices() {
+        pages
+            .entry(top_namespace(&g[idx].name))
+            .or_default()
+            .extend(g.neighbors_undirected(idx).chain([idx]));
+    }
+    pages
+}
+
+/// The graph with the tasks of each cycle merged into a single task, named
+/// after all of them, which leaves it without cycles. Also returns the task
+/// each of the original ones ended up in.
+pub fn condense(
+    g: &DiGraph<Node, Dep>,
+) -> (DiGraph<Node, Dep>, HashMap<NodeIndex, NodeIndex>) {
+    let indices = g.map(|idx, _| idx, |_, e| *e);
+    let mut moved = HashMap::new();
+    let condensed = condensation(indices, true).map(
+        |idx, members| {
+            for &m in members {
+                moved.insert(m, idx);
+            }
+            match members.as_slice() {
+                [m] => g[*m].clone(),
+                _ => {
+                    let mut names = members
+                        .iter()
+                        .map(|&m| g[m].name.as_str())
+                        .collect::<Vec<_>>();
+                    names.sort();
+                    let mut node = Node::new(names.join("\n"));
+                    node.defined = true;
+                    node
+                }
+            }
+        },
+        |_, e| *e,
+    );
+    (condensed, moved)
+}
+
+/// The graph with each leaf task, one without dependencies, merged into the
+/// task depending on it, when it is the only one. The merged tasks are listed
+/// in [`Node::leaves`]. Tasks that aren't defined are kept, so that they still
+/// show dashed. Also returns where each of the original tasks ended up.
+pub fn collapse_leaves(
+    g: &DiGraph<Node, Dep>,
+) -> (DiGraph<Node, Dep>, HashMap<NodeIndex, NodeIndex>) {
+    let parent = |idx: NodeIndex| {
+        if !g[idx].defined
+            || g.neighbors_directed(idx, Direction::Incoming)
+                .next()
+                .is_some()
+        {
+            return None;
+        }
+        let mut dependents = g.neighbors(idx).collect::<Vec<_>>();
+        dependents.dedup();
+        match dependents.as_slice() {
+            [p] if *p != idx => Some(*p),
+            _ => None,
+        }
+    };
+    let merged = g
+        .node_indices()
+        .filter_map(|idx| Some((idx, parent(idx)?)))
+        .collect::<HashMap<_, _>>();
+    let keep = g
+        .node_indices()
+        .filter(|idx| !merged.contains_key(idx))
+        .collect();
+    let (mut result, mut moved) = subgraph(g, &keep);
+    let mut leaves = merged.into_iter().collect::<Vec<_>>();
+    leaves.sort();
+    for (leaf, parent) in leaves {
+        let to = moved[&parent];
+        result[to].leaves.push(g[leaf].name.clone());
+        moved.insert(leaf, to);
+    }
+    (result, moved)
+}
+
+/// Longest distance of each task from a root task, one that no other task
+/// depends on.
+///
+/// The tasks of a cycle all get the same depth, ignoring the dependencies
+/// between them.
+pub fn depths(g: &DiGraph<Node, Dep>) -> HashMap<NodeIndex, usize> {
+    depths_with(g, |sccs, component, scc| {
+        sccs[scc]
+            .iter()
+            .flat_map(|&idx| g.neighbors(idx))
+            .all(|n| component[&n] == scc)
+    })
+}
+
+/// Like [`depths`], but from the given root tasks. Tasks that none of them
+/// depend on, directly or not, are left out.
+pub fn depths_from(
+    g: &DiGraph<Node, Dep>,
+    roots: &[NodeIndex],
+) -> HashMap<NodeIndex, usize> {
+    depths_with(g, |sccs, _, scc| {
+        sccs[scc].iter().any(|idx| roots.contains(idx))
+    })
+}
+
+/// The longest chain of dependencies, from a task that nothing depends on to
+/// one of its deepest dependencies, following [`depths`].
+///
+/// In a graph with cycles this isn't always a chain of actual dependencies,
+/// since the tasks of a cycle share their depth.
+pub fn longest_chain(g: &DiGraph<Node, Dep>) -> Vec<NodeIndex> {
+    let depths = depths(g);
+    let deepest = depths
+        .iter()
+        .max_by_key(|(idx, d)| (**d, std::cmp::Reverse(**idx)))
+        .map(|(&idx, &d)| (idx, d));
+    let (mut idx, mut d) = match deepest {
+        Some(deepest) => deepest,
+        None => return vec![],
+    };
+    let mut chain = vec![idx];
+    while d > 0 {
+        let mut dependents = g
+            .neighbors(idx)
+            .filter(|n| depths[n] + 1 == d)
+            .collect::<Vec<_>>();
+        dependents.sort();
+        match dependents.first() {
+            Some(&n) => idx = n,
+            None => break,
+        }
+        chain.push(idx);
+        d -= 1;
+    }
+    chain.reverse();
+    chain
+}
+
+/// The maximal chains of tasks that don't branch: each task of a chain but
+/// the last is the only dependency of the next one, which is the only task
+/// depending on it. Chains go from the dependency to the tasks depending on
+/// it, and have at least two tasks. Tasks of cycles are left out.
+pub fn linear_chains(g: &DiGraph<Node, Dep>) -> Vec<Vec<NodeIndex>> {
+    let in_cycle = cycles(g).into_iter().flatten().collect::<HashSet<_>>();
+    let linear = |from: NodeIndex, to: NodeIndex| {
+        !in_cycle.contains(&from)
+            && !in_cycle.contains(&to)
+            && g.neighbors_directed(from, Direction::Outgoing).count() == 1
+            && g.neighbors_directed(to, Direction::Incoming).count() == 1
+    };
+    let mut chains = vec![];
+    for start in g.node_indices() {
+        let previous = g
+            .neighbors_directed(start, Direction::Incoming)
+            .next()
+            .filter(|&p| linear(p, start));
+        if in_cycle.contains(&start) || previous.is_some() {
+            continue;
+        }
+        let mut chain = vec![start];
+        let mut idx = start;
+        while let Some(next) =
+            g.neighbors(idx).next().filter(|&n| linear(idx, n))
+        {
+            chain.push(next);
+            idx = next;
+        }
+        if chain.len() > 1 {
+            chains.push(chain);
+        }
+    }
+    chains
+}
+
+fn depths_with<F>(
+    g: &DiGraph<Node, Dep>,
+    is_root: F,
+) -> HashMap<NodeIndex, usize>
+where
+    F: Fn(&[Vec<NodeIndex>], &HashMap<NodeIndex, usize>, usize) -> bool,
+{
+    let sccs = tarjan_scc(g);
+    let mut component = HashMap::new();
+    for (i, scc) in sccs.iter().enumerate() {
+        for &idx in scc {
+            component.insert(idx, i);
+        }
+    }
+    let roots = (0..sccs.len())
+        .filter(|&i| is_root(&sccs, &component, i))
+        .collect::<HashSet<_>>();
+    let mut memo = HashMap::new();
+    let mut result = HashMap::new();
+    for (i, scc) in sccs.iter().enumerate() {
+        if let Some(d) = depth(g, i, &sccs, &component, &roots, &mut memo) {
+            for &idx in scc {
+                result.insert(idx, d);
+            }
+        }
+    }
+    result
+}
+
+fn depth(
+    g: &DiGraph<Node, Dep>,
+    scc: usize,
+    sccs: &[Vec<NodeIndex>],
+    component: &HashMap<NodeIndex, usize>,
+    roots: &HashSet<usize>,
+    memo: &mut HashMap<usize, Option<usize>>,
+) -> Option<usize> {
+    if let Some(&d) = memo.get(&scc) {
+        return d;
+    }
+    let dependents = sccs[scc]
+        .iter()
+        .flat_map(|&idx| g.neighbors(idx))
+        .map(|n| component[&n])
+        .filter(|&c| c != scc)
+        .collect::<HashSet<_>>();
+    let d = dependents
+        .into_iter()
+        .filter_map(|c| depth(g, c, sccs, component, roots, memo))
+        .map(|d| d + 1)
+        .chain(roots.contains(&scc).then_some(0))
+        .max();
+    memo.insert(scc, d);
+    d
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{
+        blocked_by_cycles, collapse_leaves, colon_names, condense, cycle_path,
+        cycles, dependency_path, dependency_paths, depths, depths_from,
+        edit_distance, feedback_edges, focus, in_namespace, linear_chains,
+        longest_chain, namespace_deps, namespace_pages, outside_namespaces,
+        plan, reachable, redundant_deps, similar_names, subgraph,
+        top_namespace, topo_groups, unmatched, Stats,
+    };
+    use crate::{build_graph, Dep, Node};
+    use indoc::indoc;
+    use petgraph::graph::{DiGraph, NodeIndex};
+    use regex::Regex;
+    use std::{collections::HashMap, io::Cursor};
+
+    fn graph(yaml: &str) -> (HashMap<String, NodeIndex>, DiGraph<Node, Dep>) {
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(
+            Cursor::new(yaml),
+            &[],
+            &mut n,
+            &mut g,
+            true,
+            &HashMap::new(),
+        )
+        .unwrap();
+        (n, g)
+    }
+
+    #[test]
+    fn test_feedback_edges() {
+        let (n, mut g) = graph(indoc! {r#"
+             tasks:
+               a:
+                 deps: [b]
+               b:
+                 deps: [c]
+               c:
+                 deps: [a, d]
+               d: {}
+               e:
+                 deps: [e]
+            "#});
+        assert_eq!(cycles(&g).len(), 2);
+        let fixes = feedback_edges(&g);
+        assert_eq!(fixes.len(), 2);
+        for (_, edges) in &fixes {
+            assert_eq!(edges.len(), 1);
+        }
+        let removed = fixes
+            .iter()
+            .flat_map(|(_, edges)| edges)
+            .map(|&e| g.edge_endpoints(e).unwrap())
+            .collect::<Vec<_>>();
+        assert!(removed.contains(&(n["e"], n["e"])));
+        for (from, to) in removed {
+            let e = g.find_edge(from, to).unwrap();
+            g.remove_edge(e);
+        }
+        assert!(cycles(&g).is_empty());
+    }
+
+    #[test]
+    fn test_cycle_path() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               a:
+                 deps: [b, c]
+               b:
+                 deps: [c]
+               c:
+                 deps: [a]
+               d:
+                 deps: [d]
+            "#});
+        let (a, c, d) = (n["a"], n["c"], n["d"]);
+        assert_eq!(cycle_path(&g, &[a, n["b"], c]), vec![a, c, a]);
+        assert_eq!(cycle_path(&g, &[d]), vec![d, d]);
+    }
+
+    #[test]
+    fn test_depths() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build, docs]
+               build:
+                 deps: [compile]
+               docs:
+                 deps: [compile]
+               compile:
+                 deps: [a]
+               a:
+                 deps: [b]
+               b:
+                 deps: [a]
+            "#});
+        let d = depths(&g);
+        assert_eq!(d[&n["release"]], 0);
+        assert_eq!(d[&n["build"]], 1);
+        assert_eq!(d[&n["compile"]], 2);
+        assert_eq!(d[&n["a"]], 3);
+        assert_eq!(d[&n["b"]], 3);
+        let d = depths_from(&g, &[n["docs"], n["a"]]);
+        assert_eq!(d[&n["docs"]], 0);
+        assert_eq!(d[&n["compile"]], 1);
+        assert_eq!(d[&n["a"]], 2);
+        assert!(!d.contains_key(&n["release"]));
+        assert!(!d.contains_key(&n["build"]));
+        let chain = longest_chain(&g);
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain[0], n["release"]);
+        assert_eq!(chain[2], n["compile"]);
+    }
+
+    #[test]
+    fn test_condense() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build]
+               build:
+                 deps: [gen, lint]
+               gen:
+                 deps: [build]
+               lint:
+                 deps: [lint]
+            "#});
+        let (condensed, moved) = condense(&g);
+        assert_eq!(condensed.node_count(), 3);
+        assert!(cycles(&condensed).is_empty());
+        let merged = moved[&n["build"]];
+        assert_eq!(moved[&n["gen"]], merged);
+        assert_eq!(condensed[merged].name, "build\ngen");
+        assert_eq!(condensed[moved[&n["release"]]].name, "release");
+        assert!(condensed.contains_edge(merged, moved[&n["release"]]));
+        assert!(condensed.contains_edge(moved[&n["lint"]], merged));
+    }
+
+    #[test]
+    fn test_collapse_leaves() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build, docs, missing]
+               build:
+                 deps: [fmt, lint]
+               fmt: {}
+               lint: {}
+               docs: {}
+               test:
+                 deps: [lint]
+            "#});
+        let (collapsed, moved) = collapse_leaves(&g);
+        // `lint` has two dependents, and `missing` isn't defined
+        let names = collapsed
+            .node_weights()
+            .map(|n| (n.name.as_str(), n.leaves.clone()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(names.len(), 5);
+        assert_eq!(names["build"], ["fmt"]);
+        assert_eq!(names["release"], ["docs"]);
+        assert!(names["lint"].is_empty());
+        assert!(names.contains_key("missing"));
+        assert_eq!(moved[&n["docs"]], moved[&n["release"]]);
+        assert_eq!(collapsed.edge_count(), 4);
+    }
+
+    #[test]
+    fn test_stats() {
+        let (_, g) = graph(indoc! {r#"
+             tasks:
+               a:
+                 deps: [b, c]
+               b:
+                 deps: [a]
+            "#});
+        let stats = Stats::new(&g);
+        assert_eq!(
+            stats,
+            Stats {
+                tasks: 2,
+                dependencies: 3,
+                cycles: 1,
+                undefined: 1
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "2 tasks \u{2022} 1 cycle \u{2022} 1 undefined"
+        );
+        let (_, g) = graph("tasks: {a: {}}");
+        assert_eq!(Stats::new(&g).to_string(), "1 task \u{2022} 0 cycles");
+    }
+
+    #[test]
+    fn test_colon_names() {
+        let yaml = indoc! {r#"
+             tasks:
+               "docs:build":
+                 deps: ["docs:gen"]
+               release:
+                 deps: ["docs:build"]
+            "#};
+        let (n, g) = graph(yaml);
+        assert_eq!(colon_names(&g), vec![n["docs:build"]]);
+        // Tasks of an included Taskfile only have `:` from their namespace
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        let yaml = Cursor::new("tasks: {build: {deps: [gen]}}");
+        let prefix = ["docs".to_string()];
+        build_graph(yaml, &prefix, &mut n, &mut g, true, &HashMap::new())
+            .unwrap();
+        assert!(colon_names(&g).is_empty());
+    }
+
+    #[test]
+    fn test_subgraph() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build, docs]
+               build:
+                 deps: [compile]
+            "#});
+        let keep = [n["release"], n["build"]].into_iter().collect();
+        let (sub, moved) = subgraph(&g, &keep);
+        assert_eq!(sub.node_count(), 2);
+        assert_eq!(sub.edge_count(), 1);
+        assert!(sub.contains_edge(moved[&n["build"]], moved[&n["release"]]));
+        assert!(!moved.contains_key(&n["docs"]));
+    }
+
+    #[test]
+    fn test_namespace_pages() {
+        assert_eq!(top_namespace("lib:docs:build"), "lib");
+        assert_eq!(top_namespace("release"), "");
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: ["lib:build", "docs"]
+               "lib:build":
+                 deps: ["lib:gen"]
+            "#});
+        let pages = namespace_pages(&g);
+        assert_eq!(pages.keys().copied().collect::<Vec<_>>(), ["", "lib"]);
+        let root = [n["release"], n["docs"], n["lib:build"]];
+        assert_eq!(pages[""], root.into_iter().collect());
+        let lib = [n["lib:build"], n["lib:gen"], n["release"]];
+        assert_eq!(pages["lib"], lib.into_iter().collect());
+    }
+
+    #[test]
+    fn test_plan() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [test, docs]
+               test:
+                 deps: [build]
+               docs:
+                 deps: [build]
+               build:
+                 deps: [gen]
+               gen: {}
+               lint:
+                 deps: [gen]
+            "#});
+        let names = |plan: Vec<NodeIndex>| {
+            plan.into_iter()
+                .map(|idx| g[idx].name.as_str())
+                .collect::<Vec<_>>()
+        };
+        let release = plan(&g, n["release"]).unwrap();
+        assert_eq!(names(release), ["gen", "build", "docs", "test", "release"]);
+        assert_eq!(names(plan(&g, n["gen"]).unwrap()), ["gen"]);
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build]
+               build:
+                 deps: [gen]
+               gen:
+                 deps: [build]
+            "#});
+        let left = plan(&g, n["release"]).unwrap_err();
+        assert_eq!(left, [n["build"], n["gen"]]);
+    }
+
+    #[test]
+    fn test_similar_names() {
+        assert_eq!(edit_distance("build", "biuld"), 2);
+        assert_eq!(edit_distance("lint", "lib:lint"), 4);
+        let (_, g) = graph(indoc! {r#"
+             tasks:
+               build:
+                 deps: [buidl]
+               built: {}
+               docs: {}
+            "#});
+        // Undefined tasks aren't suggested
+        assert_eq!(similar_names(&g, "biuld"), ["build"]);
+        assert_eq!(similar_names(&g, "bild"), ["build", "built"]);
+        assert!(similar_names(&g, "release").is_empty());
+    }
+
+    #[test]
+    fn test_focus() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build]
+               build:
+                 deps: [compile]
+               compile:
+                 deps: [gen]
+               lint:
+                 deps: [gen]
+            "#});
+        let all = [n["release"], n["build"], n["compile"], n["gen"]];
+        assert_eq!(focus(&g, n["build"], false), all.into_iter().collect());
+        let direct = [n["release"], n["build"], n["compile"]];
+        assert_eq!(focus(&g, n["build"], true), direct.into_iter().collect());
+    }
+
+    #[test]
+    fn test_unmatched() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build, "lib:test"]
+               build:
+                 deps: ["lib:build"]
+               test: {}
+            "#});
+        let pattern = Regex::new(":test$").unwrap();
+        let kept = [n["release"], n["build"], n["lib:build"], n["test"]];
+        assert_eq!(unmatched(&g, &pattern), kept.into_iter().collect());
+        let (pruned, _) = subgraph(&g, &unmatched(&g, &pattern));
+        assert_eq!(pruned.node_count(), 4);
+        assert_eq!(pruned.edge_count(), 2);
+    }
+
+    #[test]
+    fn test_outside_namespaces() {
+        assert!(in_namespace("lib:docs:html", "lib"));
+        assert!(in_namespace("lib:docs:html", "lib:docs"));
+        assert!(!in_namespace("library:build", "lib"));
+        assert!(!in_namespace("lib", "lib"));
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: ["vendor:fetch", "vendor:x:gen", "lib:build"]
+            "#});
+        let kept = [n["release"], n["lib:build"]];
+        assert_eq!(
+            outside_namespaces(&g, &["vendor".into()]),
+            kept.into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_linear_chains() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [package, docs]
+               package:
+                 deps: [build]
+               build:
+                 deps: [compile]
+               compile:
+                 deps: [gen]
+               docs:
+                 deps: [a]
+               a:
+                 deps: [b]
+               b:
+                 deps: [a]
+            "#});
+        // `release` has two dependencies, and `docs` depends on a cycle
+        assert_eq!(
+            linear_chains(&g),
+            vec![vec![n["gen"], n["compile"], n["build"], n["package"]]]
+        );
+    }
+
+    #[test]
+    fn test_dependency_path() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [test, package]
+               test:
+                 deps: [build]
+               package:
+                 deps: [build]
+               build:
+                 deps: [gen]
+            "#});
+        assert_eq!(
+            dependency_path(&g, n["release"], n["gen"]),
+            Some(vec![n["release"], n["package"], n["build"], n["gen"]])
+        );
+        assert_eq!(
+            dependency_path(&g, n["build"], n["build"]),
+            Some(vec![n["build"]])
+        );
+        assert_eq!(dependency_path(&g, n["gen"], n["release"]), None);
+    }
+
+    #[test]
+    fn test_dependency_paths() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [test, package, docs]
+               test:
+                 deps: [build]
+               package:
+                 deps: [build, gen]
+               build:
+                 deps: [gen, lint]
+               lint:
+                 deps: [build]
+            "#});
+        let (release, gen) = (n["release"], n["gen"]);
+        let (paths, complete) = dependency_paths(&g, release, gen, 10);
+        assert!(complete);
+        let (build, package, test) = (n["build"], n["package"], n["test"]);
+        // Through `lint` would go through `build` twice, and `docs` doesn't
+        // depend on `gen`
+        assert_eq!(
+            paths,
+            vec![
+                vec![release, package, build, gen],
+                vec![release, package, gen],
+                vec![release, test, build, gen],
+            ]
+        );
+        let (paths, complete) = dependency_paths(&g, release, gen, 2);
+        assert!(!complete);
+        assert_eq!(paths.len(), 2);
+        let (paths, complete) = dependency_paths(&g, release, gen, 3);
+        assert!(complete);
+        assert_eq!(paths.len(), 3);
+        assert_eq!(dependency_paths(&g, gen, release, 10), (vec![], true));
+    }
+
+    #[test]
+    fn test_topo_groups() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [test, docs]
+               test:
+                 deps: [b]
+               a:
+                 deps: [b, gen]
+               b:
+                 deps: [a]
+               docs:
+                 deps: [gen]
+            "#});
+        assert_eq!(
+            topo_groups(&g),
+            vec![
+                vec![n["gen"]],
+                vec![n["a"], n["b"]],
+                vec![n["docs"]],
+                vec![n["test"]],
+                vec![n["release"]],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blocked_by_cycles() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [test, docs]
+               test:
+                 deps: [b]
+               a:
+                 deps: [b, gen]
+               b:
+                 deps: [a]
+               docs:
+                 deps: [docs]
+               lint:
+                 deps: [c]
+               c:
+                 deps: [d]
+               d:
+                 deps: [c]
+            "#});
+        assert_eq!(
+            blocked_by_cycles(&g),
+            vec![
+                (vec![n["a"], n["b"]], vec![n["release"], n["test"]]),
+                (vec![n["c"], n["d"]], vec![n["lint"]]),
+                (vec![n["docs"]], vec![n["release"]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redundant_deps() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [package, build, gen]
+               package:
+                 deps: [build]
+               build:
+                 deps: [gen]
+               a:
+                 deps: [b, gen]
+               b:
+                 deps: [a, gen]
+            "#});
+        let found = redundant_deps(&g)
+            .into_iter()
+            .map(|(e, path)| (g.edge_endpoints(e).unwrap(), path))
+            .collect::<Vec<_>>();
+        assert_eq!(found.len(), 2, "{found:?}");
+        let (build, gen) = (n["build"], n["gen"]);
+        let (package, release) = (n["package"], n["release"]);
+        assert!(
+            found.contains(&((build, release), vec![build, package, release]))
+        );
+        assert!(found.contains(&((gen, release), vec![gen, build, release])));
+        // Either of `a` and `b` gets `gen` through the other, but not both
+    }
+
+    #[test]
+    fn test_namespace_deps() {
+        let (n, mut g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [docker, lib, "docker:push", "docker:build", "ci:x:lint"]
+               test:
+                 deps: [ci, "lib:default"]
+            "#});
+        for name in ["docker:push", "docker:build", "ci:x:lint", "lib:default"]
+        {
+            g[n[name]].defined = true;
+        }
+        assert_eq!(
+            namespace_deps(&g),
+            vec![
+                (n["docker"], vec!["docker:build", "docker:push"]),
+                (n["ci"], vec!["ci:x:lint"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reachable() {
+        let (n, g) = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build]
+               build:
+                 deps: [compile]
+               compile:
+                 deps: [build]
+               old:
+                 deps: [legacy]
+               legacy: {}
+            "#});
+        let seen = reachable(&g, &[n["release"]]);
+        assert_eq!(seen.len(), 3);
+        assert!(seen.contains(&n["compile"]));
+        assert!(!seen.contains(&n["old"]));
+        assert!(!seen.contains(&n["legacy"]));
+    }
+}
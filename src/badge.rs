@@ -0,0 +1,63 @@
+use crate::escape_xml;
+
+/// Approximate width of a character of 11px Verdana, the font of the badge:
+/// there's no text measurement without a renderer, so wide messages get a
+/// little slack instead.
+const CHAR_WIDTH: f64 = 7.0;
+
+/// Space on each side of the text of a half of the badge
+const PADDING: f64 = 6.0;
+
+fn text_width(s: &str) -> f64 {
+    s.chars().count() as f64 * CHAR_WIDTH + 2.0 * PADDING
+}
+
+/// A self-contained SVG badge in the style of shields.io, with `label` on
+/// gray on the left and `message` on `color` on the right.
+pub fn badge_svg(label: &str, message: &str, color: &str) -> String {
+    let (lw, mw) = (text_width(label), text_width(message));
+    let w = lw + mw;
+    let title = escape_xml(&format!("{label}: {message}"));
+    let (label, message) = (escape_xml(label), escape_xml(message));
+    let (lx, mx) = (lw / 2.0, lw + mw / 2.0);
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="20" role="img" aria-label="{title}">
+<title>{title}</title>
+<linearGradient id="s" x2="0" y2="100%">
+<stop offset="0" stop-color="#bbb" stop-opacity=".1"/>
+<stop offset="1" stop-opacity=".1"/>
+</linearGradient>
+<clipPath id="r"><rect width="{w}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)">
+<rect width="{lw}" height="20" fill="#555"/>
+<rect x="{lw}" width="{mw}" height="20" fill="{color}"/>
+<rect width="{w}" height="20" fill="url(#s)"/>
+</g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+<text x="{lx}" y="14">{label}</text>
+<text x="{mx}" y="14">{message}</text>
+</g>
+</svg>
+"##
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::badge::badge_svg;
+
+    #[test]
+    fn test_badge_svg() {
+        let svg = badge_svg("taskdep", "3 tasks & 1 cycle", "#e05d44");
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"192\""
+        ));
+        assert!(svg.contains("<title>taskdep: 3 tasks &amp; 1 cycle</title>"));
+        assert!(svg.contains(
+            "<rect x=\"61\" width=\"131\" height=\"20\" fill=\"#e05d44\"/>"
+        ));
+        assert!(svg.contains(
+            "<text x=\"126.5\" y=\"14\">3 tasks &amp; 1 cycle</text>"
+        ));
+    }
+}
@@ -0,0 +1,436 @@
+use crate::{
+    display_name, escape, escape_xml, node_ids, Dep, DotOptions, Node,
+};
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use std::collections::{HashMap, HashSet};
+
+/// Collapses and expands the clusters of an SVG rendered with
+/// [`DotOptions::interactive_clusters`](crate::DotOptions)
+const SCRIPT: &str = include_str!("collapse.js");
+
+/// Hides the dependencies standing in for collapsed clusters when the SVG is
+/// viewed without running its script.
+const STYLE: &str = ".taskdep-agg { display: none } \
+                     .taskdep-cluster text { cursor: pointer }";
+
+/// Fill colors of the clusters with [`DotOptions::namespace_legend`], from
+/// ColorBrewer's Pastel1, light enough for the tasks to stay readable on them
+const PALETTE: [&str; 9] = [
+    "#fbb4ae", "#b3cde3", "#ccebc5", "#decbe4", "#fed9a6", "#ffffcc",
+    "#e5d8bd", "#fddaec", "#f2f2f2",
+];
+
+/// A subgraph with the id `id` and the statements `lines`, indented.
+fn block(id: &str, lines: &[String]) -> String {
+    let body = lines
+        .iter()
+        .flat_map(|l| l.lines())
+        .map(|l| format!("    {l}\n"))
+        .collect::<String>();
+    format!("subgraph {id} {{\n{body}}}")
+}
+
+/// The namespace of a task, and the namespaces it is nested in, outermost
+/// first: `a:b:build` is in `a` and `a:b`.
+fn namespaces(name: &str) -> Vec<&str> {
+    name.match_indices(':').map(|(i, _)| &name[..i]).collect()
+}
+
+/// The namespaces of the tasks of a graph, each drawn as a cluster, and the
+/// cycles, with [`Clusters::with_cycles`].
+///
+/// With `interactive`, elements get classes telling the script in
+/// [`add_script`] when to show them: `taskdep-in-N` for those hidden when
+/// cluster `N` is collapsed, and `taskdep-agg-N` for those shown only then.
+pub(crate) struct Clusters {
+    names: Vec<String>,
+    /// The clusters each task is in, outermost first
+    chains: HashMap<NodeIndex, Vec<usize>>,
+    interactive: bool,
+    separator: Option<char>,
+    /// Whether to color the clusters and list them in a legend
+    legend: bool,
+    /// Colors of the clusters, taken in turn
+    palette: Vec<String>,
+    /// The tasks of each cycle drawn as a cluster, the namespace cluster it
+    /// is drawn in, if any, and its color, as `name="value"`
+    cycles: Vec<(Vec<NodeIndex>, Option<usize>, String)>,
+    /// The id of each task in the DOT source, by index
+    ids: Vec<String>,
+}
+
+impl Clusters {
+    pub(crate) fn new(g: &DiGraph<Node, Dep>, options: &DotOptions) -> Self {
+        let mut names = g
+            .node_weights()
+            .filter(|_| options.clusters || options.interactive_clusters)
+            .flat_map(|n| namespaces(&n.name))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        let index = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (*name, i))
+            .collect::<HashMap<_, _>>();
+        let chains = g
+            .node_indices()
+            .map(|idx| {
+                let chain = namespaces(&g[idx].name)
+                    .into_iter()
+                    .filter_map(|ns| index.get(ns).copied())
+                    .collect();
+                (idx, chain)
+            })
+            .collect();
+        Clusters {
+            names: names.into_iter().map(String::from).collect(),
+            chains,
+            interactive: options.interactive_clusters,
+            separator: options.separator,
+            legend: options.namespace_legend,
+            palette: match options.palette.is_empty() {
+                true => PALETTE.map(String::from).to_vec(),
+                false => options.palette.clone(),
+            },
+            cycles: vec![],
+            ids: node_ids(g, options),
+        }
+    }
+
+    /// Also draw each of `cycles`, as its tasks and its color, as a cluster.
+    ///
+    /// A cycle takes precedence over namespaces, as clusters can only nest:
+    /// it is drawn in the innermost namespace cluster all its tasks are in,
+    /// if any, and its tasks are left out of the clusters nested in that one.
+    pub(crate) fn with_cycles(
+        mut self,
+        cycles: Vec<(Vec<NodeIndex>, String)>,
+    ) -> Self {
+        for (tasks, color) in cycles {
+            let mut common = self.chains[&tasks[0]].clone();
+            for idx in &tasks[1..] {
+                let chain = &self.chains[idx];
+                let shared =
+                    common.iter().zip(chain).take_while(|(a, b)| a == b);
+                common.truncate(shared.count());
+            }
+            for idx in &tasks {
+                self.chains.insert(*idx, common.clone());
+            }
+            self.cycles.push((tasks, common.last().copied(), color));
+        }
+        self
+    }
+
+    /// The color of cluster `i`.
+    fn color(&self, i: usize) -> &str {
+        &self.palette[i % self.palette.len()]
+    }
+
+    fn classes(kind: &str, hidden_in: &[usize], shown_in: &[usize]) -> String {
+        let mut classes = vec!["taskdep-el".to_string(), kind.to_string()];
+        classes.extend(hidden_in.iter().map(|i| format!("taskdep-in-{i}")));
+        classes.extend(shown_in.iter().map(|i| format!("taskdep-agg-{i}")));
+        format!("class=\"{}\"", classes.join(" "))
+    }
+
+    /// Attributes for a task
+    pub(crate) fn node_attrs(&self, idx: NodeIndex) -> String {
+        if !self.interactive {
+            return String::new();
+        }
+        Self::classes("taskdep-node", &self.chains[&idx], &[])
+    }
+
+    /// Attributes for a dependency, hidden as soon as either of its tasks is
+    /// in a collapsed cluster
+    pub(crate) fn edge_attrs(&self, from: NodeIndex, to: NodeIndex) -> String {
+        if !self.interactive {
+            return String::new();
+        }
+        let mut hidden_in = self.chains[&from].clone();
+        hidden_in.extend(&self.chains[&to]);
+        hidden_in.sort();
+        hidden_in.dedup();
+        Self::classes("taskdep-edge", &hidden_in, &[])
+    }
+
+    /// DOT statements for the clusters, and, when interactive, for the
+    /// dependencies between collapsed clusters and the tasks around them.
+    pub(crate) fn statements(&self, g: &DiGraph<Node, Dep>) -> Vec<String> {
+        if self.names.is_empty() && self.cycles.is_empty() {
+            return vec![];
+        }
+        let mut statements = vec![];
+        if self.interactive {
+            statements.push("compound = true".to_string());
+        }
+        for i in 0..self.names.len() {
+            if !self.names[i].contains(':') {
+                statements.push(self.subgraph(g, i, &[]));
+            }
+        }
+        statements.extend(self.cycle_subgraphs(g, None));
+        if self.interactive {
+            statements.extend(self.aggregated_edges(g));
+        }
+        if self.legend {
+            statements.push(self.legend(g));
+        }
+        statements
+    }
+
+    /// A box listing each namespace, with the color of its cluster and the
+    /// number of tasks in it, including those of the namespaces nested in it.
+    fn legend(&self, g: &DiGraph<Node, Dep>) -> String {
+        let mut counts = HashMap::new();
+        for n in g.node_weights() {
+            for ns in namespaces(&n.name) {
+                *counts.entry(ns).or_insert(0) += 1;
+            }
+        }
+        let rows = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                format!(
+                    "<tr><td bgcolor=\"{}\" width=\"12\"></td>\
+                     <td align=\"left\">{}</td>\
+                     <td align=\"right\">{}</td></tr>",
+                    escape_xml(self.color(i)),
+                    escape_xml(&display_name(name, self.separator)),
+                    counts[name.as_str()]
+                )
+            })
+            .collect::<String>();
+        format!(
+            "legend_namespaces [ shape=\"plaintext\" label=<\
+             <table border=\"0\" cellborder=\"1\" cellspacing=\"0\" \
+             cellpadding=\"3\"><tr><td colspan=\"3\"><b>Namespaces</b></td>\
+             </tr>{rows}</table>> ]"
+        )
+    }
+
+    /// The cluster of namespace `i`, with its tasks sorted by name, so that
+    /// the layout doesn't depend on the order they were read in.
+    fn subgraph(
+        &self,
+        g: &DiGraph<Node, Dep>,
+        i: usize,
+        ancestors: &[usize],
+    ) -> String {
+        let name = &self.names[i];
+        let label = display_name(name, self.separator);
+        let mut lines = vec![format!("label = \"{}\"", escape(&label))];
+        if self.legend {
+            lines.push("style = \"filled\"".to_string());
+            lines.push(format!("fillcolor = \"{}\"", escape(self.color(i))));
+        }
+        if self.interactive {
+            lines.push(format!("id = \"taskdep-cluster-{i}\""));
+            lines.push(Self::classes("taskdep-cluster", ancestors, &[]));
+        }
+        let mut members = self
+            .chains
+            .iter()
+            .filter(|(_, chain)| chain.last() == Some(&i))
+            .map(|(&idx, _)| idx)
+            .filter(|idx| !self.cycles.iter().any(|(c, ..)| c.contains(idx)))
+            .collect::<Vec<_>>();
+        members.sort_by_key(|&idx| (&g[idx].name, idx));
+        lines.extend(members.into_iter().map(|m| self.ids[m.index()].clone()));
+        lines.extend(self.cycle_subgraphs(g, Some(i)));
+        let mut nested = ancestors.to_vec();
+        nested.push(i);
+        let prefix = format!("{name}:");
+        for (j, child) in self.names.iter().enumerate() {
+            let rest = child.strip_prefix(&prefix);
+            if rest.is_some_and(|r| !r.contains(':')) {
+                lines.push(self.subgraph(g, j, &nested));
+            }
+        }
+        block(&format!("\"cluster_{}\"", escape(name)), &lines)
+    }
+
+    /// The clusters of the cycles drawn in namespace cluster `host`, or in
+    /// none, numbered from 1 in the order they were given.
+    fn cycle_subgraphs(
+        &self,
+        g: &DiGraph<Node, Dep>,
+        host: Option<usize>,
+    ) -> Vec<String> {
+        self.cycles
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, h, _))| *h == host)
+            .map(|(n, (tasks, _, color))| {
+                let mut lines =
+                    vec![format!("label = \"cycle {}\"", n + 1), color.clone()];
+                let mut tasks = tasks.clone();
+                tasks.sort_by_key(|&idx| (&g[idx].name, idx));
+                lines.extend(
+                    tasks.into_iter().map(|t| self.ids[t.index()].clone()),
+                );
+                block(&format!("cluster_cycle_{}", n + 1), &lines)
+            })
+            .collect()
+    }
+
+    /// For a dependency crossing the boundary of some clusters, one stand-in
+    /// for each way of collapsing them: the dependency is then drawn from or
+    /// to the outermost collapsed cluster on each side.
+    fn aggregated_edges(&self, g: &DiGraph<Node, Dep>) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut statements = vec![];
+        for e in g.edge_references() {
+            let (from, to) = (e.source(), e.target());
+            let (tails, heads) = (&self.chains[&from], &self.chains[&to]);
+            let common =
+                tails.iter().zip(heads).take_while(|(a, b)| a == b).count();
+            for t in common..=tails.len() {
+                for h in common..=heads.len() {
+                    if t == tails.len() && h == heads.len() {
+                        continue;
+                    }
+                    let tail = tails.get(t);
+                    let head = heads.get(h);
+                    if !seen.insert((tail.ok_or(from), head.ok_or(to))) {
+                        continue;
+                    }
+                    let mut hidden_in = tails[..t].to_vec();
+                    hidden_in.extend(&heads[common..h]);
+                    let shown_in = tail
+                        .into_iter()
+                        .chain(head)
+                        .copied()
+                        .collect::<Vec<_>>();
+                    let mut attrs = vec![
+                        Self::classes("taskdep-agg", &hidden_in, &shown_in),
+                        "constraint = false".to_string(),
+                    ];
+                    if let Some(&t) = tail {
+                        attrs.push(format!(
+                            "ltail = \"cluster_{}\"",
+                            escape(&self.names[t])
+                        ));
+                    }
+                    if let Some(&h) = head {
+                        attrs.push(format!(
+                            "lhead = \"cluster_{}\"",
+                            escape(&self.names[h])
+                        ));
+                    }
+                    statements.push(format!(
+                        "{} -> {} [ {} ]",
+                        self.ids[from.index()],
+                        self.ids[to.index()],
+                        attrs.join(" ")
+                    ));
+                }
+            }
+        }
+        statements
+    }
+}
+
+/// Embed the script collapsing and expanding clusters in an SVG rendered with
+/// [`DotOptions::interactive_clusters`](crate::DotOptions).
+pub fn add_script(svg: &[u8]) -> Vec<u8> {
+    let svg = String::from_utf8_lossy(svg);
+    match svg.rfind("</svg>") {
+        Some(end) => format!(
+            "{}<style>{STYLE}</style>\n<script><![CDATA[\n{SCRIPT}]]></script>\n{}",
+            &svg[..end],
+            &svg[end..]
+        )
+        .into_bytes(),
+        None => svg.into_owned().into_bytes(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::clusters::{add_script, namespaces, Clusters};
+    use crate::{build_graph, DotOptions};
+    use indoc::indoc;
+    use petgraph::graph::DiGraph;
+    use std::{collections::HashMap, io::Cursor};
+
+    #[test]
+    fn test_clusters() {
+        assert_eq!(namespaces("a:b:build"), vec!["a", "a:b"]);
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 deps: ["lib:test", "lib:build", "lib:docs:html"]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            clusters: true,
+            ..Default::default()
+        };
+        let statements = Clusters::new(&g, &options).statements(&g);
+        assert_eq!(statements.len(), 1);
+        let lib = &statements[0];
+        assert!(lib.starts_with("subgraph \"cluster_lib\" {"));
+        // Sorted by name rather than in the order of the Taskfile
+        let (build, test) = (n["lib:build"].index(), n["lib:test"].index());
+        assert!(lib.contains(&format!("    {build}\n    {test}\n")));
+        assert!(lib.contains("subgraph \"cluster_lib:docs\" {"));
+        let interactive = DotOptions {
+            interactive_clusters: true,
+            ..Default::default()
+        };
+        let statements = Clusters::new(&g, &interactive).statements(&g);
+        // Both dependencies from `lib` stand in for `lib:build` and
+        // `lib:docs:html` when `lib` is collapsed, so only one is kept
+        let aggregated = statements
+            .iter()
+            .filter(|s| s.contains("taskdep-agg "))
+            .collect::<Vec<_>>();
+        assert_eq!(aggregated.len(), 2);
+        assert!(aggregated.iter().any(|s| s.contains("taskdep-agg-0")
+            && s.contains("ltail = \"cluster_lib\"")));
+        assert!(
+            aggregated
+                .iter()
+                .any(|s| s.contains("taskdep-agg-1")
+                    && s.contains("taskdep-in-0"))
+        );
+        let legend = DotOptions {
+            namespace_legend: true,
+            ..options
+        };
+        let statements = Clusters::new(&g, &legend).statements(&g);
+        assert!(statements[0].contains("    fillcolor = \"#fbb4ae\"\n"));
+        assert!(statements[0].contains("        fillcolor = \"#b3cde3\"\n"));
+        let legend = statements.last().unwrap();
+        assert!(legend.starts_with("legend_namespaces [ shape=\"plaintext\""));
+        assert!(legend.contains(
+            "<tr><td bgcolor=\"#fbb4ae\" width=\"12\"></td>\
+             <td align=\"left\">lib</td><td align=\"right\">3</td></tr>"
+        ));
+        assert!(legend.contains(
+            "<td align=\"left\">lib:docs</td><td align=\"right\">1</td>"
+        ));
+        // A single color for all
+        let legend = DotOptions {
+            clusters: true,
+            namespace_legend: true,
+            palette: vec!["gold".into()],
+            ..Default::default()
+        };
+        let statements = Clusters::new(&g, &legend).statements(&g);
+        assert!(statements[0].contains("    fillcolor = \"gold\"\n"));
+        assert!(statements[0].contains("        fillcolor = \"gold\"\n"));
+        let svg = add_script(b"<svg>\n<g/>\n</svg>\n");
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.contains("<script><![CDATA["));
+        assert!(svg.ends_with("</script>\n</svg>\n"));
+    }
+}
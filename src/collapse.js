@@ -0,0 +1,43 @@
+// Collapse and expand the namespace clusters of a graph rendered by taskdep
+// with --interactive-clusters. Clicking the label of a cluster hides its
+// tasks, and shows their dependencies from and to the cluster itself.
+//
+// Elements are tagged by class: `taskdep-in-N` ones are hidden while cluster
+// N is collapsed, and `taskdep-agg-N` ones are shown only while it is.
+(function () {
+  var collapsed = {};
+
+  function clusters(el, prefix) {
+    var result = [];
+    el.classList.forEach(function (c) {
+      if (c.indexOf(prefix) === 0) {
+        result.push(c.slice(prefix.length));
+      }
+    });
+    return result;
+  }
+
+  function isCollapsed(i) {
+    return collapsed[i];
+  }
+
+  function update() {
+    document.querySelectorAll(".taskdep-el").forEach(function (el) {
+      var shown =
+        !clusters(el, "taskdep-in-").some(isCollapsed) &&
+        clusters(el, "taskdep-agg-").every(isCollapsed);
+      el.style.display = shown ? "inline" : "none";
+    });
+  }
+
+  document.querySelectorAll(".taskdep-cluster").forEach(function (cluster) {
+    var i = cluster.id.replace("taskdep-cluster-", "");
+    cluster.querySelectorAll("text").forEach(function (label) {
+      label.addEventListener("click", function () {
+        collapsed[i] = !collapsed[i];
+        update();
+      });
+    });
+  });
+  update();
+})();
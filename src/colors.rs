@@ -0,0 +1,165 @@
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+use std::io::Read;
+
+/// Colors for some tasks, by name or by a glob pattern over names, read from
+/// a YAML or JSON mapping such as `{release: gold, "lib:*": "#1f77b4"}`.
+///
+/// A color can also be given as a mapping with the `color` of the outline and
+/// the `fill` of the box, either of them optional, such as
+/// `{release: {color: black, fill: gold}}`.
+#[derive(Debug, Default)]
+pub struct ColorMap {
+    /// Patterns and their colors, in the order of the file
+    entries: Vec<(String, TaskColors)>,
+}
+
+/// The colors given for a task
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TaskColors {
+    /// Color of the outline
+    pub color: Option<String>,
+    /// Color the box is filled with
+    pub fill: Option<String>,
+}
+
+impl ColorMap {
+    pub fn parse<R>(f: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        // Keeps the order of the keys, which JSON is a subset of
+        let mapping: Mapping = serde_yaml::from_reader(f)?;
+        let entries = mapping
+            .into_iter()
+            .map(|(pattern, color)| {
+                let expected = || {
+                    anyhow!(
+                        "expected a color for {}",
+                        serde_yaml::to_string(&pattern)
+                            .unwrap_or_default()
+                            .trim()
+                    )
+                };
+                let colors = match color {
+                    Value::String(color) => TaskColors {
+                        color: Some(color),
+                        fill: None,
+                    },
+                    Value::Mapping(colors) => {
+                        let mut given = TaskColors::default();
+                        for (key, color) in colors {
+                            let slot = match key.as_str() {
+                                Some("color") => &mut given.color,
+                                Some("fill") => &mut given.fill,
+                                _ => return Err(expected()),
+                            };
+                            match color {
+                                Value::String(color) => *slot = Some(color),
+                                _ => return Err(expected()),
+                            }
+                        }
+                        given
+                    }
+                    _ => return Err(expected()),
+                };
+                match pattern {
+                    Value::String(pattern) => Ok((pattern, colors)),
+                    _ => Err(expected()),
+                }
+            })
+            .collect::<Result<_>>()?;
+        Ok(ColorMap { entries })
+    }
+
+    /// The colors of the task `name`: those given for its exact name, or
+    /// else for the first pattern matching it.
+    pub fn colors(&self, name: &str) -> Option<&TaskColors> {
+        let exact = self.entries.iter().find(|(p, _)| p == name);
+        exact
+            .or_else(|| self.entries.iter().find(|(p, _)| glob_match(p, name)))
+            .map(|(_, colors)| colors)
+    }
+
+    /// The outline color of the task `name`, as [`ColorMap::colors`].
+    pub fn color(&self, name: &str) -> Option<&str> {
+        self.colors(name)?.color.as_deref()
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any characters,
+/// including `:`, and `?` for a single one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (
+        pattern.chars().collect::<Vec<_>>(),
+        name.chars().collect::<Vec<_>>(),
+    );
+    // Where to resume after the last `*`, and the name position it matched up
+    // to so far
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp + 1;
+                    n = sn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use crate::colors::{glob_match, ColorMap};
+    use indoc::indoc;
+
+    #[test]
+    fn test_color_map() {
+        assert!(glob_match("lib:*", "lib:docs:html"));
+        assert!(glob_match("*:test", "lib:test"));
+        assert!(glob_match("b?ild", "build"));
+        assert!(!glob_match("lib:*", "library"));
+        assert!(!glob_match("*:test", "lib:tests"));
+        let yaml = indoc! {r##"
+            "lib:*": "#1f77b4"
+            lib:release: gold
+            "*": gray
+        "##};
+        let colors = ColorMap::parse(yaml.as_bytes()).unwrap();
+        assert_eq!(colors.color("lib:release"), Some("gold"));
+        assert_eq!(colors.color("lib:build"), Some("#1f77b4"));
+        assert_eq!(colors.color("release"), Some("gray"));
+        let json = r#"{"release": "red"}"#;
+        let colors = ColorMap::parse(json.as_bytes()).unwrap();
+        assert_eq!(colors.color("release"), Some("red"));
+        assert_eq!(colors.color("build"), None);
+        assert!(ColorMap::parse("release: [red]".as_bytes()).is_err());
+        let yaml = indoc! {r##"
+            release: {color: black, fill: gold}
+            "lib:*": {fill: "#aec7e8"}
+        "##};
+        let colors = ColorMap::parse(yaml.as_bytes()).unwrap();
+        let release = colors.colors("release").unwrap();
+        assert_eq!(release.color.as_deref(), Some("black"));
+        assert_eq!(release.fill.as_deref(), Some("gold"));
+        assert_eq!(colors.color("lib:build"), None);
+        let build = colors.colors("lib:build").unwrap();
+        assert_eq!(build.fill.as_deref(), Some("#aec7e8"));
+        for invalid in ["release: {fill: [gold]}", "release: {border: gold}"] {
+            assert!(ColorMap::parse(invalid.as_bytes()).is_err(), "{invalid}");
+        }
+    }
+}
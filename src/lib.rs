@@ -0,0 +1,3141 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use petgraph::graph::{EdgeReference, NodeIndex};
+use petgraph::stable_graph::DefaultIx;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use petgraph::{
+    dot::{Config, Dot},
+    graph::DiGraph,
+};
+use serde::Deserialize;
+use serde_yaml::{self, Mapping, Value};
+use std::fs::{canonicalize, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::slice;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Debug, Formatter},
+    io::{Read, Write},
+};
+
+pub mod a11y;
+pub mod analysis;
+pub mod badge;
+pub mod clusters;
+pub mod colors;
+pub mod merge;
+pub mod provenance;
+pub mod schema;
+pub mod serve;
+pub mod source;
+
+use clusters::Clusters;
+use source::{FileSystem, Source};
+
+#[derive(Clone)]
+pub struct Node {
+    pub name: String,
+    /// Whether the task is defined, as opposed to only referenced as a dep
+    pub defined: bool,
+    /// Short description of the task, shown in its label
+    pub desc: Option<String>,
+    /// Longer description of the task, shown in its tooltip
+    pub summary: Option<String>,
+    /// Namespaces of the Taskfile defining the task, outermost first
+    pub namespace: Vec<String>,
+    /// Path of the Taskfile defining the task, as given to its [`Source`]
+    pub file: Option<String>,
+    /// Line of `file` where the task is defined, from 1, when known
+    pub line: Option<usize>,
+    /// How often Task runs the task when several tasks depend on it:
+    /// `always`, `once` or `when_changed`
+    pub run: Option<String>,
+    /// Dependencies merged into the task by [`analysis::collapse_leaves`],
+    /// listed in its label
+    pub leaves: Vec<String>,
+    /// Other Taskfiles defining the task too, which an include with
+    /// `flatten: true` put in the same namespace, and whose dependencies are
+    /// merged into it
+    pub also_defined_in: Vec<String>,
+}
+
+impl Node {
+    pub fn new(name: String) -> Self {
+        Node {
+            name,
+            defined: false,
+            desc: None,
+            summary: None,
+            namespace: vec![],
+            file: None,
+            line: None,
+            run: None,
+            leaves: vec![],
+            also_defined_in: vec![],
+        }
+    }
+
+    /// The name of the task in the Taskfile defining it, without namespaces
+    pub fn local_name(&self) -> &str {
+        let prefix = self.namespace.iter().map(|ns| ns.len() + 1).sum();
+        &self.name[prefix..]
+    }
+}
+
+/// What to show in the node labels
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Label {
+    /// The task name
+    #[default]
+    Name,
+    /// The task name, followed by its description
+    Desc,
+}
+
+/// How Taskfiles are parsed
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    /// From the extension of the file: JSON for `.json`, YAML otherwise
+    #[default]
+    Auto,
+    Yaml,
+    Json,
+}
+
+impl InputFormat {
+    /// The format of the Taskfile at `path`: this one, unless it is `Auto`.
+    pub fn resolve(self, path: &str) -> InputFormat {
+        match self {
+            InputFormat::Auto => {
+                let extension = Path::new(path).extension();
+                if extension.is_some_and(|e| e.eq_ignore_ascii_case("json")) {
+                    InputFormat::Json
+                } else {
+                    InputFormat::Yaml
+                }
+            }
+            format => format,
+        }
+    }
+}
+
+/// Color presets for the rendered graph
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Graphviz defaults: black on white
+    #[default]
+    Light,
+    /// Light text and lines on a dark background
+    Dark,
+}
+
+impl Theme {
+    fn graph_attrs(&self) -> &'static [&'static str] {
+        match self {
+            Theme::Light => &[],
+            Theme::Dark => &[r##"bgcolor="#1e1e1e""##],
+        }
+    }
+
+    fn node_attrs(&self) -> &'static [&'static str] {
+        match self {
+            Theme::Light => &[],
+            Theme::Dark => &[
+                r##"color="#c8c8c8""##,
+                r##"fontcolor="#e6e6e6""##,
+                r##"style="filled""##,
+                r##"fillcolor="#2d2d2d""##,
+            ],
+        }
+    }
+
+    fn edge_attrs(&self) -> &'static [&'static str] {
+        match self {
+            Theme::Light => &[],
+            Theme::Dark => &[r##"color="#c8c8c8""##],
+        }
+    }
+
+    /// Style of the tasks depended on without being defined, filled like
+    /// the others in [`Theme::node_attrs`]
+    fn undefined_style(&self) -> &'static str {
+        match self {
+            Theme::Light => "dashed",
+            Theme::Dark => "filled,dashed",
+        }
+    }
+
+    /// Color of the nodes and edges that are part of a cycle
+    fn cycle_color(&self) -> &'static str {
+        match self {
+            Theme::Light => "red",
+            Theme::Dark => "#ff6b6b",
+        }
+    }
+
+    /// Colors telling cycles apart, starting with [`Theme::cycle_color`]
+    fn cycle_palette(&self) -> &'static [&'static str] {
+        match self {
+            Theme::Light => &[
+                "red", "#1f77b4", "#2ca02c", "#9467bd", "#ff7f0e", "#8c564b",
+                "#e377c2", "#17becf",
+            ],
+            Theme::Dark => &[
+                "#ff6b6b", "#6baed6", "#74c476", "#b39ddb", "#fdae6b",
+                "#c49c94", "#f7b6d2", "#9edae5",
+            ],
+        }
+    }
+}
+
+/// Rendering settings for [`graph_to_dot`]
+#[derive(Debug, Default)]
+pub struct DotOptions {
+    pub label: Label,
+    /// Wrap labels at word boundaries near this width
+    pub wrap: Option<usize>,
+    pub theme: Theme,
+    /// Extra graph attributes, as `name="value"`
+    pub graph_attrs: Vec<String>,
+    /// Default attributes for every node, as `name="value"`
+    pub node_attrs: Vec<String>,
+    /// Default attributes for every edge, as `name="value"`
+    pub edge_attrs: Vec<String>,
+    /// Draw each namespace as a cluster around its tasks
+    pub clusters: bool,
+    /// Text written under the graph
+    pub footer: Option<String>,
+    /// Text written above the graph, followed by the footer if any, as a
+    /// graph has one label only
+    pub title: Option<String>,
+    /// Give each cycle its own color, instead of the same for all
+    pub distinct_cycles: bool,
+    /// Draw each cycle as a cluster around its tasks, labeled with its
+    /// number, within the innermost namespace cluster all its tasks are in
+    /// with `clusters`, and taking them out of those nested in it
+    pub cycle_clusters: bool,
+    /// Colors of the cycles with `distinct_cycles`, and of the clusters with
+    /// `namespace_legend`, taken in turn, in place of the built-in ones
+    pub palette: Vec<String>,
+    /// Show namespaces separated by this instead of `:`
+    pub separator: Option<char>,
+    /// Like `clusters`, and prepare an SVG render to collapse them with the
+    /// script added by [`clusters::add_script`]
+    pub interactive_clusters: bool,
+    /// Entries of a legend drawn beside the graph, as a description and the
+    /// attributes of the sample edge showing it, as `name="value"`
+    pub legend: Vec<(String, String)>,
+    /// End the label of each task with its numbers of dependencies and of
+    /// tasks depending on it, as `(↓IN ↑OUT)`
+    pub degrees: bool,
+    /// With `clusters`, give each cluster a color, and list the namespaces
+    /// with their colors and numbers of tasks in a box beside the graph
+    pub namespace_legend: bool,
+    /// Write task labels in a monospace font, with their lines aligned on
+    /// the left
+    pub mono: bool,
+    /// Put the tasks of each namespace on the same rank, as a band across
+    /// the graph, with the `dot` engine
+    pub rank_by_namespace: bool,
+    /// Draw the arrows from each task to its dependencies, rather than from
+    /// the dependencies to the tasks depending on them
+    pub reverse_edges: bool,
+    /// Identify the tasks in the DOT source by their names, such as
+    /// `"lib_build"`, rather than by their indices, which change whenever
+    /// tasks are added or removed; see [`node_ids`]
+    pub name_ids: bool,
+}
+
+/// Font of the task labels with [`DotOptions::mono`]
+const MONO_FONT: &str = "Courier";
+
+impl DotOptions {
+    /// DOT statements setting the graph attributes and the node and edge
+    /// defaults.
+    fn attr_statements(&self) -> Vec<String> {
+        let mut statements = vec![];
+        let label = match (&self.title, &self.footer) {
+            (Some(title), Some(footer)) => {
+                Some((format!("{title}\n{footer}"), "t"))
+            }
+            (Some(title), None) => Some((title.clone(), "t")),
+            (None, Some(footer)) => Some((footer.clone(), "b")),
+            (None, None) => None,
+        };
+        if let Some((label, loc)) = label {
+            statements.push(format!(
+                "graph [ label=\"{}\" labelloc=\"{loc}\" ]",
+                escape(&label)
+            ));
+        }
+        let mono = format!("fontname=\"{MONO_FONT}\"");
+        for (kind, theme, own, extra) in [
+            ("graph", self.theme.graph_attrs(), None, &self.graph_attrs),
+            (
+                "node",
+                self.theme.node_attrs(),
+                Some(mono.as_str()).filter(|_| self.mono),
+                &self.node_attrs,
+            ),
+            (
+                "edge",
+                self.theme.edge_attrs(),
+                // The arrowhead at the dependency, with the same layout
+                Some("dir=\"back\"").filter(|_| self.reverse_edges),
+                &self.edge_attrs,
+            ),
+        ] {
+            let attrs = theme
+                .iter()
+                .map(|a| a.to_string())
+                .chain(own.map(String::from))
+                .chain(extra.iter().cloned())
+                .collect::<Vec<_>>();
+            if !attrs.is_empty() {
+                statements.push(format!("{kind} [ {} ]", attrs.join(" ")));
+            }
+        }
+        statements
+    }
+
+    /// A cluster with a sample edge for each entry of the legend.
+    fn legend_statements(&self) -> Vec<String> {
+        if self.legend.is_empty() {
+            return vec![];
+        }
+        let mut lines = vec![
+            "subgraph cluster_legend {".to_string(),
+            "    label=\"Legend\"".to_string(),
+            "    node [ shape=\"point\" style=\"invis\" ]".to_string(),
+        ];
+        for (i, (description, attrs)) in self.legend.iter().enumerate() {
+            lines.push(format!(
+                "    legend_{i}_from -> legend_{i}_to [ label=\"{}\" {attrs} ]",
+                escape(description)
+            ));
+        }
+        lines.push("}".to_string());
+        vec![lines.join("\n")]
+    }
+}
+
+impl Debug for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// A dependency: an edge from a task to a task depending on it, with the
+/// flags of a dependency given as a mapping, such as `{task: lint, silent:
+/// true}`. They don't change what depends on what.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dep {
+    /// Task runs the dependency without printing its commands
+    pub silent: bool,
+    /// Task goes on when the dependency fails
+    pub ignore_error: bool,
+}
+
+/// A dependency graph, with its tasks looked up by name rather than by
+/// petgraph index
+#[derive(Debug)]
+pub struct TaskGraph {
+    graph: DiGraph<Node, Dep>,
+    nodes: HashMap<String, NodeIndex>,
+    cycles: Vec<Vec<NodeIndex>>,
+}
+
+/// The tasks next to a task of a [`TaskGraph`]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TaskNeighbors {
+    /// Direct dependencies, sorted by name
+    pub deps: Vec<String>,
+    /// Tasks depending directly on the task, sorted by name
+    pub dependents: Vec<String>,
+    /// Whether the task is part of a cycle, depending on itself indirectly
+    pub in_cycle: bool,
+}
+
+impl TaskGraph {
+    pub fn new(graph: DiGraph<Node, Dep>) -> Self {
+        let nodes = graph
+            .node_indices()
+            .map(|idx| (graph[idx].name.clone(), idx))
+            .collect();
+        let cycles = analysis::cycles(&graph);
+        TaskGraph {
+            graph,
+            nodes,
+            cycles,
+        }
+    }
+
+    pub fn graph(&self) -> &DiGraph<Node, Dep> {
+        &self.graph
+    }
+
+    /// The index of the task `name` in [`TaskGraph::graph`].
+    pub fn index(&self, name: &str) -> Option<NodeIndex> {
+        self.nodes.get(name).copied()
+    }
+
+    pub fn node(&self, name: &str) -> Option<&Node> {
+        self.nodes.get(name).map(|&idx| &self.graph[idx])
+    }
+
+    /// The tasks of the cycle the task `name` is part of, itself included, or
+    /// `None` if it's in no cycle.
+    pub fn cycle(&self, name: &str) -> Option<&[NodeIndex]> {
+        let idx = self.index(name)?;
+        let cycle = self.cycles.iter().find(|c| c.contains(&idx))?;
+        Some(cycle)
+    }
+
+    /// The direct dependencies and dependents of the task `name`, or `None`
+    /// if there is no such task.
+    pub fn neighbors(&self, name: &str) -> Option<TaskNeighbors> {
+        let idx = *self.nodes.get(name)?;
+        let names = |direction| {
+            let mut names = self
+                .graph
+                .neighbors_directed(idx, direction)
+                .map(|n| self.graph[n].name.clone())
+                .collect::<Vec<_>>();
+            names.sort();
+            names.dedup();
+            names
+        };
+        Some(TaskNeighbors {
+            deps: names(Direction::Incoming),
+            dependents: names(Direction::Outgoing),
+            in_cycle: self.cycle(name).is_some(),
+        })
+    }
+
+    /// The dependencies, as pairs of the name of the dependency and of the
+    /// task depending on it, in the direction of the edges of the graph.
+    pub fn edges_named(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.graph.edge_references().map(|e| {
+            (
+                self.graph[e.source()].name.clone(),
+                self.graph[e.target()].name.clone(),
+            )
+        })
+    }
+}
+
+/// A parsed Taskfile, before includes are resolved
+#[derive(Debug, Default)]
+pub struct Taskfile {
+    /// Static top-level `vars`
+    pub vars: HashMap<String, String>,
+    pub tasks: Vec<TaskInfo>,
+    pub includes: Vec<IncludeInfo>,
+    /// How Task prints the output of commands: `interleaved`, `group` or
+    /// `prefixed`
+    pub output: Option<String>,
+    /// How Task decides whether a task is up to date
+    pub method: Option<String>,
+    /// Default `run` mode of the tasks
+    pub run: Option<String>,
+    /// Version of Task the Taskfile is written for, such as `3` or `3.28`
+    pub version: Option<String>,
+}
+
+/// A task as defined in a Taskfile
+#[derive(Debug, Default)]
+pub struct TaskInfo {
+    /// Name of the task, without the namespace of any include
+    pub name: String,
+    pub desc: Option<String>,
+    pub summary: Option<String>,
+    /// Names of the dependencies, relative to the task's Taskfile
+    pub deps: Vec<String>,
+    /// Flags of the dependencies given as mappings, by name
+    pub dep_flags: HashMap<String, Dep>,
+    pub internal: bool,
+    pub aliases: Vec<String>,
+    /// Shell commands of the task, leaving out calls to other tasks
+    pub cmds: Vec<String>,
+    /// Line of the Taskfile where the task is defined, from 1, when known
+    pub line: Option<usize>,
+    /// `run` mode of the task, if it sets one
+    pub run: Option<String>,
+}
+
+/// An entry of a Taskfile's `includes` section
+#[derive(Debug, Default)]
+pub struct IncludeInfo {
+    pub namespace: String,
+    /// Path of the included Taskfile, possibly with `{{.VAR}}` templates
+    pub taskfile: String,
+    /// Variables the include passes down to the included Taskfile
+    pub vars: HashMap<String, String>,
+    pub flatten: bool,
+    pub optional: bool,
+    pub internal: bool,
+}
+
+impl IncludeInfo {
+    /// Path of the included Taskfile, with its templates expanded using the
+    /// variables in scope of the including Taskfile.
+    pub fn path(&self, scope: &HashMap<String, String>) -> Result<String> {
+        let mut scope = scope.clone();
+        scope.extend(self.vars.clone());
+        expand_vars(&self.taskfile, &scope)
+            .with_context(|| format!("include `{}`", self.namespace))
+    }
+}
+
+impl Taskfile {
+    /// Variables in scope of the Taskfile, given the ones passed down by the
+    /// include that pulled it in, which take precedence over its own.
+    pub fn scope(
+        &self,
+        vars: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        let mut scope = self.vars.clone();
+        scope.extend(vars.clone());
+        scope
+    }
+}
+
+/// Collect the static `vars` of a mapping as strings.
+///
+/// Dynamic variables (`sh:`) can't be evaluated without running them, so they
+/// are left out.
+fn static_vars(vars: Option<&Value>) -> Result<HashMap<String, String>> {
+    let mut result = HashMap::new();
+    if let Some(vars) = vars {
+        let vars = vars
+            .as_mapping()
+            .ok_or_else(|| anyhow!("vars is not a mapping"))?;
+        for (name, value) in vars {
+            let name = name
+                .as_str()
+                .ok_or_else(|| anyhow!("var name is not a string"))?;
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                _ => continue,
+            };
+            result.insert(name.to_string(), value);
+        }
+    }
+    Ok(result)
+}
+
+/// Replace every `{{.VAR}}` in `s` with its value from `vars`.
+fn expand_vars(s: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| anyhow!("unterminated template in `{s}`"))?;
+        let expr = rest[start + 2..start + end].trim();
+        let name = expr
+            .strip_prefix('.')
+            .ok_or_else(|| anyhow!("unsupported template `{expr}` in `{s}`"))?;
+        let value = vars
+            .get(name)
+            .ok_or_else(|| anyhow!("undefined variable `{name}` in `{s}`"))?;
+        result.push_str(&rest[..start]);
+        result.push_str(value);
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_sequence())
+        .map(|seq| {
+            seq.iter()
+                .filter_map(|s| s.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_includes(yaml: &HashMap<String, Value>) -> Result<Vec<IncludeInfo>> {
+    let mut result = vec![];
+    let incs = match yaml.get("includes") {
+        Some(incs) => incs,
+        None => return Ok(result),
+    };
+    let namespaces = incs
+        .as_mapping()
+        .ok_or_else(|| anyhow!("includes is not a mapping"))?;
+    for (namespace, descr) in namespaces {
+        let name = namespace
+            .as_str()
+            .ok_or_else(|| anyhow!("namespace is not a string"))?;
+        let flag =
+            |key| descr.get(key).and_then(|v| v.as_bool()).unwrap_or_default();
+        let (taskfile, vars) = match descr {
+            Value::String(s) => (s.as_str(), HashMap::new()),
+            Value::Mapping(m) => (
+                m.get("taskfile").and_then(|t| t.as_str()).ok_or_else(
+                    || anyhow!("couldn't find taskfile name to include"),
+                )?,
+                static_vars(m.get("vars"))?,
+            ),
+            _ => bail!("incorrect type for an include"),
+        };
+        result.push(IncludeInfo {
+            namespace: name.into(),
+            taskfile: taskfile.into(),
+            vars,
+            flatten: flag("flatten"),
+            optional: flag("optional"),
+            internal: flag("internal"),
+        });
+    }
+    Ok(result)
+}
+
+fn parse_task(name: &str, descr: &Mapping) -> Result<TaskInfo> {
+    let mut deps = vec![];
+    let mut dep_flags = HashMap::new();
+    if let Some(ds) = descr.get("deps") {
+        for dep in ds
+            .as_sequence()
+            .ok_or_else(|| anyhow!("deps is not a list"))?
+        {
+            let dep_name = match dep {
+                Value::String(n) => n,
+                Value::Mapping(m) => {
+                    let name = m
+                        .get("task")
+                        .and_then(|t| t.as_str())
+                        .ok_or_else(|| anyhow!("couldn't find name of task"))?;
+                    // Other keys, such as `vars`, are left alone
+                    let flag = |key| {
+                        m.get(key).and_then(Value::as_bool).unwrap_or_default()
+                    };
+                    let flags = Dep {
+                        silent: flag("silent"),
+                        ignore_error: flag("ignore_error"),
+                    };
+                    if flags != Dep::default() {
+                        dep_flags.insert(name.to_string(), flags);
+                    }
+                    name
+                }
+                _ => bail!("incorrect type for a dependency"),
+            };
+            deps.push(dep_name.to_string());
+        }
+    }
+    let cmds = descr
+        .get("cmds")
+        .and_then(|c| c.as_sequence())
+        .map(|cmds| {
+            cmds.iter()
+                .filter_map(|c| match c {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Mapping(m) => {
+                        m.get("cmd").and_then(|c| c.as_str()).map(String::from)
+                    }
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(TaskInfo {
+        name: name.into(),
+        desc: descr.get("desc").and_then(|d| d.as_str()).map(String::from),
+        summary: descr
+            .get("summary")
+            .and_then(Value::as_str)
+            .map(String::from),
+        deps,
+        dep_flags,
+        internal: descr
+            .get("internal")
+            .and_then(|i| i.as_bool())
+            .unwrap_or_default(),
+        aliases: strings(descr.get("aliases")),
+        cmds,
+        line: None,
+        run: descr.get("run").and_then(Value::as_str).map(String::from),
+    })
+}
+
+/// Read the top-level keys of every document in a YAML stream.
+///
+/// Later documents add their `tasks`, `includes` and `vars` to the ones of
+/// the earlier documents, replacing entries with the same name. Any other key
+/// keeps the value of the first document defining it.
+fn read_documents<R>(f: R) -> Result<HashMap<String, Value>>
+where
+    R: Read,
+{
+    let mut yaml: HashMap<String, Value> = HashMap::new();
+    for document in serde_yaml::Deserializer::from_reader(f) {
+        let mut value = Value::deserialize(document)?;
+        if value.is_null() {
+            continue;
+        }
+        // Tasks often share settings with `<<: *anchor`, the anchor being
+        // kept under a top-level key such as `x-defaults`, ignored otherwise
+        value.apply_merge()?;
+        let document: HashMap<String, Value> = serde_yaml::from_value(value)?;
+        for (key, value) in document {
+            match (yaml.get_mut(&key), value) {
+                (Some(Value::Mapping(old)), Value::Mapping(new))
+                    if ["tasks", "includes", "vars"]
+                        .contains(&key.as_str()) =>
+                {
+                    old.extend(new)
+                }
+                (Some(_), _) => (),
+                (None, value) => {
+                    yaml.insert(key, value);
+                }
+            }
+        }
+    }
+    Ok(yaml)
+}
+
+/// Lines where the tasks of a YAML Taskfile are defined, from 1.
+///
+/// `serde_yaml` doesn't keep track of locations, so this is a second pass
+/// over the text, looking for the keys of the top-level `tasks` mappings in
+/// block style. Tasks defined in flow style (`tasks: {build: ...}`) are left
+/// out.
+fn task_lines(text: &str) -> HashMap<String, usize> {
+    let mut lines = HashMap::new();
+    let mut in_tasks = false;
+    let mut indent = None;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let depth = line.len() - trimmed.len();
+        if depth == 0 {
+            in_tasks = yaml_key(trimmed).is_some_and(|(key, rest)| {
+                key == "tasks" && (rest.is_empty() || rest.starts_with('#'))
+            });
+            indent = None;
+        } else if in_tasks && *indent.get_or_insert(depth) == depth {
+            if let Some((key, _)) = yaml_key(trimmed) {
+                // Later documents override the tasks of earlier ones
+                lines.insert(key, i + 1);
+            }
+        }
+    }
+    lines
+}
+
+/// Split a line of a YAML mapping into its key, unquoted, and the rest of
+/// the line after the `:`.
+fn yaml_key(line: &str) -> Option<(String, &str)> {
+    let (key, rest) = match line.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = line[1..].find(quote)? + 1;
+            (line[1..end].to_string(), line[end + 1..].strip_prefix(':')?)
+        }
+        _ => {
+            let end = line.find(": ").or_else(|| {
+                line.trim_end()
+                    .ends_with(':')
+                    .then(|| line.trim_end().len() - 1)
+            })?;
+            (line[..end].trim_end().to_string(), &line[end + 1..])
+        }
+    };
+    Some((key, rest.trim()))
+}
+
+/// Parse a Taskfile without following its includes.
+///
+/// A stream of several YAML documents is merged as described in
+/// [`read_documents`].
+pub fn parse<R>(f: R) -> Result<Taskfile>
+where
+    R: Read,
+{
+    parse_as(f, InputFormat::Yaml)
+}
+
+/// Like [`parse`], for a Taskfile in the given format.
+pub fn parse_as<R>(mut f: R, format: InputFormat) -> Result<Taskfile>
+where
+    R: Read,
+{
+    let mut lines = HashMap::new();
+    let yaml = match format {
+        InputFormat::Auto | InputFormat::Yaml => {
+            let mut text = String::new();
+            f.read_to_string(&mut text)?;
+            lines = task_lines(&text);
+            read_documents(text.as_bytes())?
+        }
+        InputFormat::Json => {
+            let json: HashMap<String, serde_json::Value> =
+                serde_json::from_reader(f)?;
+            json.into_iter()
+                .map(|(key, value)| Ok((key, serde_yaml::to_value(value)?)))
+                .collect::<Result<_>>()?
+        }
+    };
+    let includes = parse_includes(&yaml)?;
+    let tasks = yaml
+        .get("tasks")
+        .ok_or_else(|| anyhow!("tasks not found"))?
+        .as_mapping()
+        .ok_or_else(|| anyhow!("tasks is not a mapping"))?;
+    let tasks = tasks
+        .iter()
+        .map(|(task, descr)| {
+            let name = task
+                .as_str()
+                .ok_or_else(|| anyhow!("task name is not a string"))?;
+            let descr = descr
+                .as_mapping()
+                .ok_or_else(|| anyhow!("task is not a mapping"))?;
+            let mut task = parse_task(name, descr)?;
+            task.line = lines.get(name).copied();
+            Ok(task)
+        })
+        .collect::<Result<_>>()?;
+    // `output` is either a mode, or a mapping from `group` to its options
+    let output = match yaml.get("output") {
+        Some(Value::Mapping(m)) => m.keys().next().and_then(Value::as_str),
+        Some(output) => output.as_str(),
+        None => None,
+    };
+    Ok(Taskfile {
+        vars: static_vars(yaml.get("vars"))?,
+        tasks,
+        includes,
+        output: output.map(String::from),
+        method: yaml.get("method").and_then(Value::as_str).map(String::from),
+        run: yaml.get("run").and_then(Value::as_str).map(String::from),
+        // Often written as a number, as in `version: 3`
+        version: match yaml.get("version") {
+            Some(Value::String(version)) => Some(version.clone()),
+            Some(Value::Number(version)) => Some(version.to_string()),
+            _ => None,
+        },
+    })
+}
+
+/// Write the include hierarchy of `taskfile` as an indented tree.
+///
+/// `ancestors` holds the canonical paths of the Taskfiles including this one,
+/// so that include cycles are marked instead of followed.
+pub fn print_includes<W>(
+    taskfile: &str,
+    vars: &HashMap<String, String>,
+    ancestors: &mut Vec<PathBuf>,
+    out: &mut W,
+) -> Result<()>
+where
+    W: Write,
+{
+    let f = File::open(taskfile).map_err(|e| anyhow!("{taskfile}: {e}"))?;
+    let parsed = parse(f)?;
+    let scope = parsed.scope(vars);
+    for include in &parsed.includes {
+        let indent = "  ".repeat(ancestors.len());
+        let taskfile = include.path(&scope)?;
+        let mut flags = vec![];
+        for (set, flag) in [
+            (include.flatten, "flatten"),
+            (include.optional, "optional"),
+            (include.internal, "internal"),
+        ] {
+            if set {
+                flags.push(flag);
+            }
+        }
+        let path = canonicalize(&taskfile);
+        match &path {
+            Ok(p) if ancestors.contains(p) => flags.push("cycle"),
+            Ok(_) => (),
+            Err(_) => flags.push("missing"),
+        }
+        let shown = match &path {
+            Ok(p) => p.to_string_lossy().into_owned(),
+            Err(_) => taskfile.clone(),
+        };
+        let flags = if flags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", flags.join(", "))
+        };
+        writeln!(out, "{indent}{}: {shown}{flags}", include.namespace)?;
+        if let Ok(p) = path {
+            if !ancestors.contains(&p) {
+                ancestors.push(p);
+                print_includes(&taskfile, &include.vars, ancestors, out)?;
+                ancestors.pop();
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn build_graph<R>(
+    f: R,
+    prefix: &[String],
+    nodes: &mut HashMap<String, NodeIndex<DefaultIx>>,
+    graph: &mut DiGraph<Node, Dep>,
+    includes: bool,
+    vars: &HashMap<String, String>,
+) -> Result<()>
+where
+    R: Read,
+{
+    let taskfile = parse(f)?;
+    add_taskfile(
+        &taskfile,
+        &mut FileSystem::default(),
+        prefix,
+        nodes,
+        graph,
+        includes.then(Vec::new).as_mut(),
+        vars,
+    )
+    .map(|_| ())
+}
+
+/// Like [`build_graph`], reading the Taskfile at `path` and its includes from
+/// `source`.
+pub fn build_graph_from(
+    source: &mut dyn Source,
+    path: &str,
+    prefix: &[String],
+    nodes: &mut HashMap<String, NodeIndex<DefaultIx>>,
+    graph: &mut DiGraph<Node, Dep>,
+    includes: bool,
+    vars: &HashMap<String, String>,
+) -> Result<()> {
+    let mut ancestors = includes.then(Vec::new);
+    add_file(source, path, prefix, nodes, graph, ancestors.as_mut(), vars)
+        .map(|_| ())
+}
+
+/// The Taskfiles including the one being read, from the root, each with its
+/// [`Source::canonical`] path and the path it was included as
+type Ancestors = Vec<(PathBuf, String)>;
+
+/// Like [`build_graph_from`], returning the tasks the Taskfile defines at
+/// `prefix`, itself or through flattened includes, with the Taskfile
+/// defining each. Includes are followed unless `ancestors` is `None`.
+fn add_file(
+    source: &mut dyn Source,
+    path: &str,
+    prefix: &[String],
+    nodes: &mut HashMap<String, NodeIndex<DefaultIx>>,
+    graph: &mut DiGraph<Node, Dep>,
+    mut ancestors: Option<&mut Ancestors>,
+    vars: &HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    if let Some(ancestors) = ancestors.as_deref_mut() {
+        // Compared as canonical paths, as a Taskfile can be included again
+        // through a symlink, or as `./a/../b.yaml` rather than `b.yaml`
+        let canonical = source.canonical(path);
+        if let Some(i) = ancestors.iter().position(|(c, _)| *c == canonical) {
+            let cycle = ancestors[i..]
+                .iter()
+                .map(|(_, path)| path.as_str())
+                .chain([path])
+                .collect::<Vec<_>>();
+            bail!("include cycle: {}", cycle.join(" -> "));
+        }
+        ancestors.push((canonical, path.to_string()));
+    }
+    let taskfile = parse_as(source.open(path)?, source.format(path))
+        .with_context(|| path.to_string())?;
+    let mut defined = add_taskfile(
+        &taskfile,
+        source,
+        prefix,
+        nodes,
+        graph,
+        ancestors.as_deref_mut(),
+        vars,
+    )?;
+    if let Some(ancestors) = ancestors {
+        ancestors.pop();
+    }
+    for task in &taskfile.tasks {
+        let name = [prefix, slice::from_ref(&task.name)].concat().join(":");
+        let node = &mut graph[nodes[&name]];
+        node.file = Some(path.to_string());
+        node.line = task.line;
+        node.run = task.run.clone().or_else(|| taskfile.run.clone());
+        defined.insert(name, path.to_string());
+    }
+    Ok(defined)
+}
+
+fn add_taskfile(
+    taskfile: &Taskfile,
+    source: &mut dyn Source,
+    prefix: &[String],
+    nodes: &mut HashMap<String, NodeIndex<DefaultIx>>,
+    graph: &mut DiGraph<Node, Dep>,
+    mut ancestors: Option<&mut Ancestors>,
+    vars: &HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    // The tasks of flattened includes, by name, with their Taskfile
+    let mut flattened = HashMap::<String, String>::new();
+    if ancestors.is_some() {
+        let scope = taskfile.scope(vars);
+        for include in &taskfile.includes {
+            let path = include.path(&scope)?;
+            let namespace = match include.flatten {
+                true => prefix.to_vec(),
+                false => [prefix, slice::from_ref(&include.namespace)].concat(),
+            };
+            let defined = add_file(
+                source,
+                &path,
+                &namespace,
+                nodes,
+                graph,
+                ancestors.as_deref_mut(),
+                &include.vars,
+            )?;
+            if !include.flatten {
+                continue;
+            }
+            for (name, file) in defined {
+                if let Some(other) = flattened.insert(name.clone(), file) {
+                    graph[nodes[&name]].also_defined_in.push(other);
+                }
+            }
+        }
+    }
+    for task in &taskfile.tasks {
+        let idx = add_task(task, prefix, nodes, graph);
+        if let Some(other) = flattened.get(&graph[idx].name) {
+            graph[idx].also_defined_in.push(other.clone());
+        }
+    }
+    Ok(flattened)
+}
+
+/// Add a task and the edges from its dependencies, returning its node.
+fn add_task(
+    task: &TaskInfo,
+    prefix: &[String],
+    nodes: &mut HashMap<String, NodeIndex<DefaultIx>>,
+    graph: &mut DiGraph<Node, Dep>,
+) -> NodeIndex<DefaultIx> {
+    let name = [prefix, slice::from_ref(&task.name)].concat().join(":");
+    let idx = *nodes
+        .entry(name.clone())
+        .or_insert_with(|| graph.add_node(Node::new(name.clone())));
+    graph[idx].defined = true;
+    graph[idx].namespace = prefix.to_vec();
+    if task.desc.is_some() {
+        graph[idx].desc = task.desc.clone();
+    }
+    if task.summary.is_some() {
+        graph[idx].summary = task.summary.clone();
+    }
+    for dep_name in &task.deps {
+        let full_dep_name =
+            [prefix, slice::from_ref(dep_name)].concat().join(":");
+        nodes.entry(full_dep_name.clone()).or_insert_with(|| {
+            graph.add_node(Node::new(full_dep_name.clone()))
+        });
+        let flags = task.dep_flags.get(dep_name).copied().unwrap_or_default();
+        graph.add_edge(nodes[&full_dep_name], nodes[&name], flags);
+    }
+    idx
+}
+
+/// Override tasks of an already built graph with the ones in an overlay
+/// Taskfile.
+///
+/// Every task defined in the overlay replaces the dependencies of the task
+/// with the same name, or is added if it didn't exist: the last definition
+/// wins. Task and dependency names in the overlay are fully qualified (e.g.
+/// `docs:build`), and the overlay's own `includes` are not followed.
+/// Undefined tasks that are no longer referenced by anything are dropped.
+pub fn apply_overlay<R>(
+    f: R,
+    nodes: &mut HashMap<String, NodeIndex<DefaultIx>>,
+    graph: &mut DiGraph<Node, Dep>,
+) -> Result<()>
+where
+    R: Read,
+{
+    let overlay = parse(f)?;
+    for task in &overlay.tasks {
+        if let Some(&idx) = nodes.get(&task.name) {
+            while let Some(e) = graph.first_edge(idx, Direction::Incoming) {
+                graph.remove_edge(e);
+            }
+        }
+        add_task(task, &[], nodes, graph);
+    }
+    graph.retain_nodes(|g, idx| {
+        g[idx].defined || g.neighbors_undirected(idx).next().is_some()
+    });
+    *nodes = graph
+        .node_indices()
+        .map(|idx| (graph[idx].name.clone(), idx))
+        .collect();
+    Ok(())
+}
+
+/// Break `s` into lines of about `width` characters at word boundaries.
+///
+/// Words longer than `width` are kept whole.
+fn wrap(s: &str, width: usize) -> String {
+    let mut lines: Vec<String> = vec![];
+    for word in s.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.into()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Escape `s` for a quoted DOT string, keeping `\n` as a line break.
+pub fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Escape `s` for the text or a quoted attribute of an XML document, such as
+/// an SVG, and for an HTML-like label.
+pub(crate) fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// How a task or a namespace is shown, with the separator of
+/// [`DotOptions::separator`]
+fn display_name(name: &str, separator: Option<char>) -> String {
+    match separator {
+        Some(separator) => name.replace(':', &separator.to_string()),
+        None => name.to_string(),
+    }
+}
+
+/// The label of task `idx` of `g`.
+fn node_label(
+    g: &DiGraph<Node, Dep>,
+    idx: NodeIndex,
+    options: &DotOptions,
+) -> String {
+    let n = &g[idx];
+    let mut lines = vec![display_name(&n.name, options.separator)];
+    if options.label == Label::Desc {
+        // The first line of the summary, for tasks with only a summary
+        let summary = n.summary.as_deref().and_then(|s| s.lines().next());
+        lines.extend(n.desc.as_deref().or(summary).map(String::from));
+    }
+    lines.extend(
+        n.leaves
+            .iter()
+            .map(|leaf| format!("+ {}", display_name(leaf, options.separator))),
+    );
+    if options.degrees {
+        lines.push(format!(
+            "(\u{2193}{} \u{2191}{})",
+            g.neighbors_directed(idx, Direction::Incoming).count(),
+            g.neighbors_directed(idx, Direction::Outgoing).count()
+        ));
+    }
+    if let Some(width) = options.wrap {
+        lines = lines.iter().map(|l| wrap(l, width)).collect();
+    }
+    if options.mono {
+        // Graphviz aligns on the left the lines ending with `\l`
+        return lines
+            .join("\n")
+            .lines()
+            .map(|line| format!("{}\\l", escape(line)))
+            .collect();
+    }
+    escape(&lines.join("\n"))
+}
+
+pub fn graph_to_dot(g: &DiGraph<Node, Dep>, options: &DotOptions) -> String {
+    graph_to_dot_with(g, options, |_| String::new(), |_, _| String::new())
+}
+
+/// Render the graph as DOT, with extra styling for nodes and edges.
+///
+/// `node_style` receives the name of each task, and `edge_style` the names of
+/// the dependency and of the task depending on it. Both return Graphviz
+/// attributes as `name="value"` pairs separated by spaces, or an empty string.
+/// They are written after the built-in ones (cycle coloring, dangling
+/// dependencies), so any attribute they set takes precedence.
+pub fn graph_to_dot_with<N, E>(
+    g: &DiGraph<Node, Dep>,
+    options: &DotOptions,
+    node_style: N,
+    edge_style: E,
+) -> String
+where
+    N: Fn(&str) -> String,
+    E: Fn(&str, &str) -> String,
+{
+    dot_with_cycles(g, options, analysis::cycles(g), node_style, edge_style)
+}
+
+/// [`graph_to_dot_with`], with the `cycles` of `g` found already.
+fn dot_with_cycles<N, E>(
+    g: &DiGraph<Node, Dep>,
+    options: &DotOptions,
+    mut cycles: Vec<Vec<NodeIndex>>,
+    node_style: N,
+    edge_style: E,
+) -> String
+where
+    N: Fn(&str) -> String,
+    E: Fn(&str, &str) -> String,
+{
+    for cycle in &mut cycles {
+        cycle.sort();
+    }
+    cycles.sort();
+    let cycle_of = cycles
+        .iter()
+        .enumerate()
+        .flat_map(|(i, c)| c.iter().map(move |&idx| (idx, i)))
+        .collect::<HashMap<_, _>>();
+    let cycle_color = |i: usize| {
+        let color = match (options.distinct_cycles, &options.palette[..]) {
+            (true, []) => {
+                let palette = options.theme.cycle_palette();
+                palette[i % palette.len()].to_string()
+            }
+            (true, palette) => escape(&palette[i % palette.len()]),
+            (false, _) => options.theme.cycle_color().to_string(),
+        };
+        format!("color=\"{color}\"")
+    };
+    let clusters = (options.clusters
+        || options.interactive_clusters
+        || options.cycle_clusters)
+        .then(|| {
+            let clusters = Clusters::new(g, options);
+            match options.cycle_clusters {
+                true => clusters.with_cycles(
+                    cycles
+                        .iter()
+                        .enumerate()
+                        .map(|(i, c)| (c.clone(), cycle_color(i)))
+                        .collect(),
+                ),
+                false => clusters,
+            }
+        });
+    let edge_attrs = |e: EdgeReference<Dep>| {
+        let mut attrs = vec![];
+        let from = cycle_of.get(&e.source());
+        if let Some(&i) = from.filter(|&c| cycle_of.get(&e.target()) == Some(c))
+        {
+            attrs.push(cycle_color(i));
+        }
+        if let Some(clusters) = &clusters {
+            attrs.push(clusters.edge_attrs(e.source(), e.target()));
+        }
+        attrs.push(edge_style(&g[e.source()].name, &g[e.target()].name));
+        attrs.retain(|a| !a.is_empty());
+        attrs.join(" ")
+    };
+    let node_attrs = |idx: NodeIndex| {
+        let n = &g[idx];
+        let mut attrs =
+            vec![format!("label = \"{}\"", node_label(g, idx, options))];
+        if let Some(&i) = cycle_of.get(&idx) {
+            attrs.push(cycle_color(i));
+        }
+        if !n.defined {
+            attrs
+                .push(format!("style=\"{}\"", options.theme.undefined_style()));
+        }
+        if let Some(tooltip) = n.summary.as_ref().or(n.desc.as_ref()) {
+            attrs.push(format!("tooltip=\"{}\"", escape(tooltip)));
+        }
+        if let Some(clusters) = &clusters {
+            attrs.push(clusters.node_attrs(idx));
+        }
+        attrs.push(node_style(&n.name));
+        attrs.retain(|a| !a.is_empty());
+        attrs.join(" ")
+    };
+    let ids = node_ids(g, options);
+    let dot = match options.name_ids {
+        // Laid out like the source `Dot` writes, with other ids
+        true => {
+            let mut dot = "digraph {\n".to_string();
+            for idx in g.node_indices() {
+                let id = &ids[idx.index()];
+                dot.push_str(&format!("    {id} [ {}]\n", node_attrs(idx)));
+            }
+            for e in g.edge_references() {
+                dot.push_str(&format!(
+                    "    {} -> {} [ {}]\n",
+                    ids[e.source().index()],
+                    ids[e.target().index()],
+                    edge_attrs(e)
+                ));
+            }
+            dot.push_str("}\n");
+            dot
+        }
+        false => format!(
+            "{:?}",
+            Dot::with_attr_getters(
+                g,
+                &[Config::EdgeNoLabel, Config::NodeNoLabel],
+                &|_, e| edge_attrs(e),
+                &|_, (idx, _)| node_attrs(idx),
+            )
+        ),
+    };
+    let dot = insert_statements(dot, &options.attr_statements());
+    let dot = match &clusters {
+        Some(clusters) => append_statements(dot, &clusters.statements(g)),
+        None => dot,
+    };
+    let dot = append_statements(dot, &options.legend_statements());
+    match options.rank_by_namespace {
+        true => append_statements(dot, &rank_statements(g, &ids)),
+        false => dot,
+    }
+}
+
+/// The id of each task in the DOT source, by index: the index itself, or
+/// with [`DotOptions::name_ids`], its name with any character other than an
+/// ASCII letter, digit or `_` replaced by `_`, such as `"lib_build"`.
+///
+/// Names that end up the same get `_2`, `_3` and so on, in the order of the
+/// names, so ids don't depend on the order tasks were read in, and so do
+/// those that would be the id of a node of a legend.
+pub fn node_ids(g: &DiGraph<Node, Dep>, options: &DotOptions) -> Vec<String> {
+    if !options.name_ids {
+        return g
+            .node_indices()
+            .map(|idx| idx.index().to_string())
+            .collect();
+    }
+    let mut order = g.node_indices().collect::<Vec<_>>();
+    order.sort_by_key(|&idx| (&g[idx].name, idx));
+    let mut taken = HashSet::new();
+    let mut ids = vec![String::new(); g.node_count()];
+    for idx in order {
+        let base = g[idx]
+            .name
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c,
+                false => '_',
+            })
+            .collect::<String>();
+        let mut id = base.clone();
+        for n in 2.. {
+            if !is_legend_id(&id) && taken.insert(id.clone()) {
+                break;
+            }
+            id = format!("{base}_{n}");
+        }
+        ids[idx.index()] = format!("\"{id}\"");
+    }
+    ids
+}
+
+/// Whether `id` is, or could be, the id of a node drawn for a legend, such as
+/// `legend_namespaces` or `legend_0_from`, whether or not the graph has one,
+/// so that showing a legend doesn't change the ids of tasks.
+fn is_legend_id(id: &str) -> bool {
+    let Some(rest) = id.strip_prefix("legend_") else {
+        return false;
+    };
+    let entry = rest
+        .strip_suffix("_from")
+        .or_else(|| rest.strip_suffix("_to"));
+    rest == "namespaces"
+        || entry.is_some_and(|i| {
+            !i.is_empty() && i.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+/// A `rank=same` subgraph for the tasks of each namespace, from the prefix of
+/// their name, for [`DotOptions::rank_by_namespace`].
+fn rank_statements(g: &DiGraph<Node, Dep>, ids: &[String]) -> Vec<String> {
+    let mut namespaces = BTreeMap::<_, Vec<_>>::new();
+    for idx in g.node_indices() {
+        if let Some((namespace, _)) = g[idx].name.rsplit_once(':') {
+            namespaces
+                .entry(namespace)
+                .or_default()
+                .push(&ids[idx.index()]);
+        }
+    }
+    namespaces
+        .into_values()
+        .filter(|tasks| tasks.len() > 1)
+        .map(|tasks| {
+            let tasks =
+                tasks.iter().map(|i| format!("{i}; ")).collect::<String>();
+            format!("{{ rank=same; {tasks}}}")
+        })
+        .collect()
+}
+
+/// Extra attributes for some tasks and dependencies, for [`Styles::to_dot`]
+#[derive(Clone, Debug, Default)]
+pub struct Styles {
+    nodes: HashMap<String, Vec<String>>,
+    edges: HashMap<(String, String), Vec<String>>,
+}
+
+impl Styles {
+    /// Add attributes, as `name="value"`, to the node of a task.
+    pub fn node(&mut self, name: &str, attrs: impl Into<String>) {
+        self.nodes
+            .entry(name.into())
+            .or_default()
+            .push(attrs.into());
+    }
+
+    /// Add attributes, as `name="value"`, to the edge from the dependency
+    /// `from` to the task `to`.
+    pub fn edge(&mut self, from: &str, to: &str, attrs: impl Into<String>) {
+        self.edges
+            .entry((from.into(), to.into()))
+            .or_default()
+            .push(attrs.into());
+    }
+
+    /// Render the graph as DOT, with these styles added by
+    /// [`graph_to_dot_with`].
+    pub fn to_dot(
+        &self,
+        g: &DiGraph<Node, Dep>,
+        options: &DotOptions,
+    ) -> String {
+        self.to_dot_with_cycles(g, options, analysis::cycles(g))
+    }
+
+    /// [`Styles::to_dot`], with the `cycles` of `g` found already, as by
+    /// [`analysis::cycles`], such as to time finding them apart.
+    pub fn to_dot_with_cycles(
+        &self,
+        g: &DiGraph<Node, Dep>,
+        options: &DotOptions,
+        cycles: Vec<Vec<NodeIndex>>,
+    ) -> String {
+        dot_with_cycles(
+            g,
+            options,
+            cycles,
+            |name| {
+                self.nodes
+                    .get(name)
+                    .map(|a| a.join(" "))
+                    .unwrap_or_default()
+            },
+            |from, to| {
+                self.edges
+                    .get(&(from.into(), to.into()))
+                    .map(|a| a.join(" "))
+                    .unwrap_or_default()
+            },
+        )
+    }
+}
+
+/// Fill color for a task at `depth`, on a gradient from yellow for the roots
+/// to blue for the deepest tasks at `max`.
+pub fn heat_color(depth: usize, max: usize) -> String {
+    let t = if max == 0 {
+        0.0
+    } else {
+        depth as f64 / max as f64
+    };
+    format!("{:.3} 0.450 1.000", 0.15 + 0.5 * t)
+}
+
+/// Add `statements` at the end of the body of the graph in `dot`.
+fn append_statements(dot: String, statements: &[String]) -> String {
+    match dot.rfind('}') {
+        Some(end) if !statements.is_empty() => {
+            let statements = statements
+                .iter()
+                .flat_map(|s| s.lines())
+                .map(|l| format!("    {l}\n"))
+                .collect::<String>();
+            format!("{}{statements}{}", &dot[..end], &dot[end..])
+        }
+        _ => dot,
+    }
+}
+
+/// Insert `statements` at the top of the body of a DOT graph.
+fn insert_statements(dot: String, statements: &[String]) -> String {
+    match dot.split_once('\n') {
+        Some((head, body)) if !statements.is_empty() => {
+            let statements = statements
+                .iter()
+                .map(|s| format!("    {s}\n"))
+                .collect::<String>();
+            format!("{head}\n{statements}{body}")
+        }
+        _ => dot,
+    }
+}
+
+/// Write the graph as JSON, streaming it to `out` without building it in
+/// memory first.
+///
+/// The result has a `nodes` array, with the `name`, `defined` flag, `desc`,
+/// `file` and `line` of each task, and an `edges` array, with the names of the
+/// dependency (`from`) and of the task depending on it (`to`) for each edge.
+pub fn write_json<W>(g: &DiGraph<Node, Dep>, mut out: W) -> Result<()>
+where
+    W: Write,
+{
+    out.write_all(b"{\"nodes\":[")?;
+    for (i, n) in g.node_weights().enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        out.write_all(b"\n{\"name\":")?;
+        serde_json::to_writer(&mut out, &n.name)?;
+        write!(out, ",\"defined\":{},\"desc\":", n.defined)?;
+        serde_json::to_writer(&mut out, &n.desc)?;
+        out.write_all(b",\"file\":")?;
+        serde_json::to_writer(&mut out, &n.file)?;
+        out.write_all(b",\"line\":")?;
+        serde_json::to_writer(&mut out, &n.line)?;
+        out.write_all(b"}")?;
+    }
+    out.write_all(b"\n],\"edges\":[")?;
+    for (i, e) in g.edge_references().enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        out.write_all(b"\n{\"from\":")?;
+        serde_json::to_writer(&mut out, &g[e.source()].name)?;
+        out.write_all(b",\"to\":")?;
+        serde_json::to_writer(&mut out, &g[e.target()].name)?;
+        out.write_all(b"}")?;
+    }
+    out.write_all(b"\n]}\n")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Add the tasks and dependencies of a graph written by [`write_json`] to
+/// `graph`, failing on the first entry that doesn't fit its shape.
+///
+/// Only `name` is needed for tasks, which are taken as defined unless
+/// `defined` says otherwise. Their namespaces are taken from their names, as
+/// the Taskfiles that would tell `a:b` from task `b` of namespace `a` are
+/// gone.
+pub fn read_json<R>(
+    f: R,
+    nodes: &mut HashMap<String, NodeIndex<DefaultIx>>,
+    graph: &mut DiGraph<Node, Dep>,
+) -> Result<()>
+where
+    R: Read,
+{
+    let json: serde_json::Value = serde_json::from_reader(f)?;
+    let list = |key: &str| {
+        json.get(key)
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| anyhow!("expected an object with an array `{key}`"))
+    };
+    let (tasks, deps) = (list("nodes")?, list("edges")?);
+    let text = |entry: &serde_json::Value, key: &str| match entry.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(anyhow!("expected a string for `{key}`")),
+    };
+    for (i, task) in tasks.iter().enumerate() {
+        let context = || format!("nodes[{i}]");
+        let name = text(task, "name")
+            .and_then(|name| name.ok_or_else(|| anyhow!("missing `name`")))
+            .with_context(context)?;
+        if nodes.contains_key(&name) {
+            bail!("{}: task `{name}` is listed already", context());
+        }
+        let mut node = Node::new(name.clone());
+        node.defined = match task.get("defined") {
+            None => true,
+            Some(defined) => defined
+                .as_bool()
+                .ok_or_else(|| anyhow!("expected a boolean for `defined`"))
+                .with_context(context)?,
+        };
+        node.desc = text(task, "desc").with_context(context)?;
+        node.file = text(task, "file").with_context(context)?;
+        node.line = match task.get("line") {
+            None | Some(serde_json::Value::Null) => None,
+            Some(line) => Some(
+                line.as_u64()
+                    .ok_or_else(|| anyhow!("expected a number for `line`"))
+                    .with_context(context)? as usize,
+            ),
+        };
+        if node.defined {
+            node.namespace = name.split(':').map(String::from).collect();
+            node.namespace.pop();
+        }
+        nodes.insert(name, graph.add_node(node));
+    }
+    for (i, dep) in deps.iter().enumerate() {
+        let context = || format!("edges[{i}]");
+        let task = |key| {
+            let name =
+                text(dep, key)?.ok_or_else(|| anyhow!("missing `{key}`"))?;
+            nodes
+                .get(&name)
+                .copied()
+                .ok_or_else(|| anyhow!("no task `{name}` in `nodes`"))
+        };
+        let from = task("from").with_context(context)?;
+        let to = task("to").with_context(context)?;
+        graph.update_edge(from, to, Dep::default());
+    }
+    Ok(())
+}
+
+pub fn graph_to_image(g: &DiGraph<Node, Dep>, format: &str) -> Result<Output> {
+    dot_to_image(graph_to_dot(g, &DotOptions::default()), format, &[])
+}
+
+/// Render DOT source with Graphviz into the given output `format`, passing
+/// `args` to the `dot` command.
+pub fn dot_to_image(
+    contents: String,
+    format: &str,
+    args: &[String],
+) -> Result<Output> {
+    dot_to_image_with(contents, format, args, &RenderOptions::default())
+}
+
+/// How to wait for Graphviz in [`dot_to_image_with`]
+#[derive(Default)]
+pub struct RenderOptions {
+    /// Kill `dot` and fail when it takes longer than this
+    pub timeout: Option<Duration>,
+    /// Show a spinner on stderr while `dot` runs, once it takes a while
+    pub progress: bool,
+    /// The `dot` command to run, instead of the one found on `PATH`
+    pub dot: Option<PathBuf>,
+}
+
+/// Check that `path` is a file that can be run as a command.
+fn check_executable(path: &Path) -> Result<()> {
+    let metadata = std::fs::metadata(path)
+        .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+    if !metadata.is_file() {
+        bail!("{}: not a file", path.display());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            bail!("{}: not executable", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// How long `dot` runs before the spinner shows up, so that quick renders
+/// don't flicker
+const SPINNER_DELAY: Duration = Duration::from_millis(500);
+
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+
+/// How long to wait before checking on `dot` for the first time, doubled
+/// after each check up to [`SPINNER_TICK`], so that quick renders finish
+/// without waiting for a whole tick
+const FIRST_POLL: Duration = Duration::from_millis(1);
+
+/// Like [`dot_to_image`], waiting for `dot` as told by `render`.
+pub fn dot_to_image_with(
+    contents: String,
+    format: &str,
+    args: &[String],
+    render: &RenderOptions,
+) -> Result<Output> {
+    let mut command = match &render.dot {
+        Some(path) => {
+            check_executable(path).context("can't run the `dot` command")?;
+            Command::new(path)
+        }
+        None => Command::new(option_env!("DOTPATH").unwrap_or("dot")),
+    };
+    let mut dot = command
+        .arg(format!("-T{format}"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("command `dot` not found (please, make sure `graphviz` is installed)")?;
+    let mut stdin = dot
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("couldn't open stdin"))?;
+    let stdin_write = thread::spawn(move || {
+        stdin
+            .write_all(contents.as_bytes())
+            .with_context(|| "couldn't write to stdin")
+    });
+    let read_stdout = read_all(dot.stdout.take());
+    let read_stderr = read_all(dot.stderr.take());
+    let start = Instant::now();
+    let mut spinner = "|/-\\".chars().cycle();
+    let mut spinning = false;
+    let mut poll = FIRST_POLL;
+    let status = loop {
+        // With nothing to do in the meantime, just wait
+        if render.timeout.is_none() && !render.progress {
+            break dot.wait().with_context(|| "couldn't run `dot`")?;
+        }
+        if let Some(status) =
+            dot.try_wait().with_context(|| "couldn't run `dot`")?
+        {
+            break status;
+        }
+        let elapsed = start.elapsed();
+        if render.timeout.is_some_and(|t| elapsed > t) {
+            // Errors are left out: `dot` may have just finished
+            let _ = dot.kill();
+            let _ = dot.wait();
+            if spinning {
+                eprint!("\r\x1b[K");
+            }
+            bail!("`dot` took longer than {}s", elapsed.as_secs());
+        }
+        if render.progress && elapsed > SPINNER_DELAY {
+            eprint!("\rrendering with dot... {}", spinner.next().unwrap());
+            spinning = true;
+        }
+        let left = render.timeout.map_or(poll, |t| t.saturating_sub(elapsed));
+        thread::sleep(poll.min(left));
+        poll = (poll * 2).min(SPINNER_TICK);
+    };
+    if spinning {
+        eprint!("\r\x1b[K");
+    }
+    let written = stdin_write
+        .join()
+        .map_err(|e| anyhow!("stdin_write: {e:?}"))?;
+    let stderr = read_stderr.join().map_err(|e| anyhow!("stderr: {e:?}"))??;
+    check_written(written, &stderr)?;
+    Ok(Output {
+        status,
+        stdout: read_stdout.join().map_err(|e| anyhow!("stdout: {e:?}"))??,
+        stderr,
+    })
+}
+
+/// The outcome of writing the DOT source to `dot`. When `dot` stops reading
+/// early, such as on a syntax error, the write fails with a broken pipe, and
+/// what `dot` wrote to `stderr` is the error that matters.
+fn check_written(written: Result<()>, stderr: &[u8]) -> Result<()> {
+    let stderr = String::from_utf8_lossy(stderr);
+    match written {
+        Err(e) if !stderr.trim().is_empty() => {
+            Err(e.context(format!("`dot` failed: {}", stderr.trim())))
+        }
+        written => written,
+    }
+}
+
+/// The version of the `task` command found on `PATH`, such as `3.38.0`, from
+/// what `task --version` prints.
+pub fn task_version() -> Result<String> {
+    let output = Command::new("task")
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .context("command `task` not found")?;
+    if !output.status.success() {
+        bail!(
+            "`task --version` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    // Such as `Task version: v3.38.0 (h1:...)`, or just `3.40.0`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .map(String::from)
+        .ok_or_else(|| {
+            anyhow!("unexpected output of `task --version`: {}", stdout.trim())
+        })
+}
+
+/// Whether Task at version `installed` runs Taskfiles declaring `version`:
+/// it must be of the same major version, and at least as recent.
+pub fn version_supported(version: &str, installed: &str) -> bool {
+    let numbers = |v: &str| {
+        v.split(['.', '-', '+'])
+            .map_while(|n| n.parse::<u64>().ok())
+            .collect::<Vec<_>>()
+    };
+    let (version, installed) = (numbers(version), numbers(installed));
+    let padded = |v: &[u64]| {
+        (0..version.len().max(installed.len()))
+            .map(|i| v.get(i).copied().unwrap_or_default())
+            .collect::<Vec<_>>()
+    };
+    version.first() == installed.first()
+        && padded(&installed) >= padded(&version)
+}
+
+/// Read a pipe of a child process to its end in another thread, so that the
+/// child doesn't block on a full pipe.
+fn read_all<R>(pipe: Option<R>) -> thread::JoinHandle<Result<Vec<u8>>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut contents = vec![];
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut contents)
+                .with_context(|| "couldn't read from `dot`")?;
+        }
+        Ok(contents)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        apply_overlay, build_graph, build_graph_from, check_executable,
+        check_written, graph_to_dot, graph_to_dot_with, graph_to_image,
+        node_ids, parse, parse_as, print_includes, read_json,
+        source::{FileSystem, ZipSource},
+        version_supported, wrap, write_json, Dep, DotOptions, InputFormat,
+        Label, Node, TaskGraph, TaskNeighbors, Theme,
+    };
+    use indoc::{formatdoc, indoc};
+    use petgraph::prelude::DiGraph;
+    use std::{
+        collections::{HashMap, HashSet},
+        fs::{canonicalize, File},
+        io::{Cursor, Result, Write},
+    };
+    use tempdir::TempDir;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    #[test]
+    fn test_build_graph() -> Result<()> {
+        let yaml = Cursor::new(String::from(indoc! {r#"
+             foo: 1
+             tasks:
+               foo:
+                 desc: desc
+                 deps:
+                   - bar
+                   - baz
+               bar:
+                 deps:
+                   - task: spam
+                     params: params
+               baz:
+                 deps:
+                   - spam
+               spam:
+                 desc: spam
+               eggs:
+                 desc: no deps
+            "#}));
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(
+            yaml,
+            &["foo".into()],
+            &mut n,
+            &mut g,
+            true,
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(g.node_count(), 5);
+        assert_eq!(g.edge_count(), 4);
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::useless_conversion)]
+    fn test_build_graph_with_includes() -> Result<()> {
+        let inc1 = indoc! {r#"
+            tasks:
+              task1_inc1:
+                deps:
+                    - task2_inc1
+              task2_inc1:
+                descr: descr
+        "#};
+        let inc2 = indoc! {r#"
+            tasks:
+              task1_inc2:
+                foo: 1
+        "#};
+        let temp = TempDir::new("taskdep")?;
+        let inc1_filename = temp.path().join("inc1.yaml");
+        let inc2_filename = temp.path().join("inc2.yaml");
+        let mut inc1_file = File::create(&inc1_filename)?;
+        write!(&mut inc1_file, "{}", inc1)?;
+        let mut inc2_file = File::create(&inc2_filename)?;
+        write!(&mut inc2_file, "{}", inc2)?;
+        let yaml = Cursor::new(String::from(formatdoc! {r#"
+             foo: 1
+             includes:
+               inc1: {f1}
+               inc2:
+                 taskfile: {f2}
+             tasks:
+               foo:
+                 deps:
+                   - bar
+                   - baz
+                   - inc1:task1_inc1
+                   - inc2:task1_inc2
+            "#,
+            f1 = inc1_filename.to_string_lossy(),
+            f2 = inc2_filename.to_string_lossy()
+        }));
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let i = graph_to_image(&g, "svg").unwrap();
+        let out_filename = temp.path().join("out.svg");
+        let mut out = File::create(out_filename)?;
+        out.write_all(&i.stdout)?;
+        assert_eq!(g.node_count(), 6);
+        assert_eq!(g.edge_count(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_graph_with_flattened_includes() -> Result<()> {
+        let temp = TempDir::new("taskdep")?;
+        let dir = temp.path().to_string_lossy();
+        let files = [
+            (
+                "Taskfile.yaml",
+                formatdoc! {r#"
+                    includes:
+                      lib:
+                        taskfile: {dir}/lib.yaml
+                        flatten: true
+                    tasks:
+                      release:
+                        deps: [build, docs:html]
+                      test:
+                        deps: [gen]
+                "#},
+            ),
+            (
+                "lib.yaml",
+                formatdoc! {r#"
+                    includes:
+                      docs: {dir}/docs.yaml
+                    tasks:
+                      build:
+                        deps: [docs:html]
+                      test:
+                        deps: [build]
+                "#},
+            ),
+            ("docs.yaml", "tasks:\n  html: {}\n".to_string()),
+        ];
+        for (name, contents) in files {
+            File::create(temp.path().join(name))?
+                .write_all(contents.as_bytes())?;
+        }
+        let root = temp.path().join("Taskfile.yaml");
+        let root = root.to_string_lossy();
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph_from(
+            &mut FileSystem::default(),
+            &root,
+            &[],
+            &mut n,
+            &mut g,
+            true,
+            &HashMap::new(),
+        )
+        .unwrap();
+        let mut names = n.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["build", "docs:html", "gen", "release", "test"]);
+        assert!(g[n["build"]].defined);
+        assert!(g[n["build"]].namespace.is_empty());
+        assert!(g[n["docs:html"]].defined);
+        assert!(g.contains_edge(n["build"], n["release"]));
+        assert!(g.contains_edge(n["docs:html"], n["build"]));
+        assert_eq!(g[n["test"]].file.as_deref(), Some(root.as_ref()));
+        assert_eq!(g[n["test"]].also_defined_in, [format!("{dir}/lib.yaml")]);
+        assert!(g.contains_edge(n["gen"], n["test"]));
+        assert!(g.contains_edge(n["build"], n["test"]));
+        assert!(g[n["build"]].also_defined_in.is_empty());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_include_cycle_through_symlink() -> Result<()> {
+        let temp = TempDir::new("taskdep")?;
+        let dir = temp.path().to_string_lossy();
+        std::os::unix::fs::symlink(temp.path(), temp.path().join("link"))?;
+        let files = [
+            (
+                "a.yaml",
+                format!("includes:\n  b: {dir}/b.yaml\ntasks:\n  a: {{}}\n"),
+            ),
+            (
+                "b.yaml",
+                format!("includes:\n  a: {dir}/link/a.yaml\ntasks: {{}}\n"),
+            ),
+            // The same Taskfile twice, by another path, is no cycle
+            (
+                "c.yaml",
+                format!(
+                    "includes:\n  x: {dir}/d.yaml\n  y: {dir}/link/d.yaml\n\
+                     tasks: {{}}\n"
+                ),
+            ),
+            ("d.yaml", "tasks:\n  d: {}\n".to_string()),
+        ];
+        for (name, contents) in files {
+            File::create(temp.path().join(name))?
+                .write_all(contents.as_bytes())?;
+        }
+        let build = |path: &str| {
+            let mut n = HashMap::new();
+            let mut g = DiGraph::new();
+            build_graph_from(
+                &mut FileSystem::default(),
+                path,
+                &[],
+                &mut n,
+                &mut g,
+                true,
+                &HashMap::new(),
+            )
+            .map(|_| n)
+        };
+        let e = build(&format!("{dir}/a.yaml")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "include cycle: {dir}/a.yaml -> {dir}/b.yaml -> \
+                 {dir}/link/a.yaml"
+            )
+        );
+        let n = build(&format!("{dir}/c.yaml")).unwrap();
+        assert!(n.contains_key("x:d") && n.contains_key("y:d"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_graph_without_includes() {
+        let yaml = Cursor::new(indoc! {r#"
+             includes:
+               inc: does-not-exist.yaml
+             tasks:
+               foo:
+                 deps:
+                   - inc:bar
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, false, &HashMap::new()).unwrap();
+        assert_eq!(g.node_count(), 2);
+        assert!(g[n["foo"]].defined);
+        assert!(!g[n["inc:bar"]].defined);
+    }
+
+    #[test]
+    fn test_build_graph_with_templated_include() -> Result<()> {
+        let temp = TempDir::new("taskdep")?;
+        let mut inc = File::create(temp.path().join("Taskfile.yaml"))?;
+        write!(&mut inc, "tasks:\n  build:\n    desc: build\n")?;
+        let yaml = Cursor::new(formatdoc! {r#"
+             vars:
+               ROOT: {root}
+             includes:
+               mod:
+                 taskfile: "{{{{.ROOT}}}}/{{{{.NAME}}}}"
+                 vars:
+                   NAME: Taskfile.yaml
+             tasks:
+               foo:
+                 deps:
+                   - mod:build
+            "#,
+            root = temp.path().to_string_lossy()
+        });
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        assert_eq!(g.node_count(), 2);
+        assert!(g[n["mod:build"]].defined);
+
+        let yaml = Cursor::new(indoc! {r#"
+             includes:
+               mod: "{{.MISSING}}/Taskfile.yaml"
+             tasks: {}
+            "#});
+        let err = build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new())
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("undefined variable `MISSING`"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_includes() -> Result<()> {
+        let temp = TempDir::new("taskdep")?;
+        let a = temp.path().join("a.yaml");
+        let b = temp.path().join("b.yaml");
+        let mut a_file = File::create(&a)?;
+        write!(
+            &mut a_file,
+            "includes:\n  b:\n    taskfile: {}\n    optional: true\ntasks: {{}}\n",
+            b.to_string_lossy()
+        )?;
+        let mut b_file = File::create(&b)?;
+        write!(
+            &mut b_file,
+            "includes:\n  a: {}\n  gone: {}\ntasks: {{}}\n",
+            a.to_string_lossy(),
+            temp.path().join("gone.yaml").to_string_lossy()
+        )?;
+        let a = canonicalize(&a)?;
+        let b = canonicalize(&b)?;
+        let mut out = vec![];
+        let mut ancestors = vec![a.clone()];
+        print_includes(
+            &a.to_string_lossy(),
+            &HashMap::new(),
+            &mut ancestors,
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            format!("  b: {} [optional]", b.to_string_lossy())
+        );
+        assert_eq!(lines[1], format!("    a: {} [cycle]", a.to_string_lossy()));
+        assert!(
+            lines[2].starts_with("    gone: ")
+                && lines[2].ends_with("[missing]")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse() {
+        let yaml = Cursor::new(indoc! {r#"
+             vars:
+               DIR: build
+             output:
+               group:
+                 begin: '::group::{{.TASK}}'
+             method: timestamp
+             run: when_changed
+             includes:
+               docs:
+                 taskfile: docs/Taskfile.yaml
+                 internal: true
+             tasks:
+               build:
+                 desc: Build everything
+                 summary: |
+                   Build the binaries and the docs.
+
+                   Generated code is updated first.
+                 aliases: [b]
+                 deps:
+                   - gen
+                   - task: docs:render
+                 cmds:
+                   - cargo build
+                   - cmd: cp target {{.DIR}}
+                   - task: gen
+               gen:
+                 internal: true
+                 run: once
+            "#});
+        let taskfile = parse(yaml).unwrap();
+        assert_eq!(taskfile.vars["DIR"], "build");
+        assert_eq!(taskfile.output.as_deref(), Some("group"));
+        assert_eq!(taskfile.method.as_deref(), Some("timestamp"));
+        assert_eq!(taskfile.run.as_deref(), Some("when_changed"));
+        assert_eq!(taskfile.includes.len(), 1);
+        assert_eq!(taskfile.includes[0].namespace, "docs");
+        assert!(taskfile.includes[0].internal);
+        assert_eq!(taskfile.tasks.len(), 2);
+        let build = &taskfile.tasks[0];
+        assert_eq!(build.name, "build");
+        assert_eq!(build.desc.as_deref(), Some("Build everything"));
+        assert_eq!(
+            build.summary.as_deref(),
+            Some("Build the binaries and the docs.\n\nGenerated code is updated first.\n")
+        );
+        assert_eq!(build.deps, ["gen", "docs:render"]);
+        assert_eq!(build.aliases, ["b"]);
+        assert_eq!(build.cmds, ["cargo build", "cp target {{.DIR}}"]);
+        assert_eq!(build.run, None);
+        assert!(taskfile.tasks[1].internal);
+        assert_eq!(taskfile.tasks[1].run.as_deref(), Some("once"));
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let yaml = indoc! {r#"
+             tasks:
+               foo:
+                 desc: desc
+                 deps: [bar, {task: baz}]
+               bar: {}
+            "#};
+        let json = r#"{
+            "tasks": {
+                "foo": {"desc": "desc", "deps": ["bar", {"task": "baz"}]},
+                "bar": {}
+            }
+        }"#;
+        let from_yaml = parse(yaml.as_bytes()).unwrap();
+        let from_json = parse_as(json.as_bytes(), InputFormat::Json).unwrap();
+        assert_eq!(from_json.tasks.len(), from_yaml.tasks.len());
+        for (j, y) in from_json.tasks.iter().zip(&from_yaml.tasks) {
+            assert_eq!(
+                (&j.name, &j.desc, &j.deps),
+                (&y.name, &y.desc, &y.deps)
+            );
+        }
+        assert_eq!(
+            InputFormat::Auto.resolve("Taskfile.json"),
+            InputFormat::Json
+        );
+        assert_eq!(InputFormat::Auto.resolve("ci.tasks"), InputFormat::Yaml);
+        assert_eq!(
+            InputFormat::Yaml.resolve("Taskfile.json"),
+            InputFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn test_dep_flags() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 deps:
+                   - {task: lint, silent: true}
+                   - {task: test, ignore_error: true, vars: {CI: true}}
+                   - build
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let dep = |name: &str| {
+            let e = g.find_edge(n[name], n["release"]).unwrap();
+            g[e]
+        };
+        assert_eq!(
+            dep("lint"),
+            Dep {
+                silent: true,
+                ignore_error: false
+            }
+        );
+        assert_eq!(
+            dep("test"),
+            Dep {
+                silent: false,
+                ignore_error: true
+            }
+        );
+        assert_eq!(dep("build"), Dep::default());
+    }
+
+    #[test]
+    fn test_parse_anchors() {
+        let yaml = indoc! {r#"
+             version: '3'
+             _templates:
+               go: &go
+                 deps: [gen]
+                 cmds: [go build]
+             x-release: &release
+               desc: Publish it
+             tasks:
+               build:
+                 <<: *go
+               release:
+                 <<: [*go, *release]
+                 deps: [build]
+            "#};
+        let taskfile = parse(yaml.as_bytes()).unwrap();
+        let mut names = taskfile
+            .tasks
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["build", "release"]);
+        let task =
+            |name| taskfile.tasks.iter().find(|t| t.name == name).unwrap();
+        assert_eq!(task("build").deps, ["gen"]);
+        assert_eq!(task("build").cmds, ["go build"]);
+        // Keys of the task itself win over merged ones
+        assert_eq!(task("release").deps, ["build"]);
+        assert_eq!(task("release").desc.as_deref(), Some("Publish it"));
+    }
+
+    #[test]
+    fn test_task_neighbors() {
+        let mut nodes = HashMap::new();
+        let mut graph = DiGraph::new();
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               build:
+                 deps: [gen, lint, gen]
+               gen:
+                 deps: [build]
+               release:
+                 deps: [build]
+            "#});
+        let prefix = ["lib".to_string()];
+        build_graph(
+            yaml,
+            &prefix,
+            &mut nodes,
+            &mut graph,
+            true,
+            &HashMap::new(),
+        )
+        .unwrap();
+        let tasks = TaskGraph::new(graph);
+        assert_eq!(
+            tasks.neighbors("lib:build"),
+            Some(TaskNeighbors {
+                deps: vec!["lib:gen".into(), "lib:lint".into()],
+                dependents: vec!["lib:gen".into(), "lib:release".into()],
+                in_cycle: true,
+            })
+        );
+        let release = tasks.neighbors("lib:release").unwrap();
+        assert_eq!(release.deps, ["lib:build"]);
+        assert!(release.dependents.is_empty());
+        assert!(!release.in_cycle);
+        assert_eq!(tasks.neighbors("build"), None);
+        assert!(!tasks.node("lib:lint").unwrap().defined);
+        let build = tasks.index("lib:build").unwrap();
+        assert_eq!(tasks.graph()[build].name, "lib:build");
+        assert_eq!(tasks.index("build"), None);
+        let mut cycle = tasks.cycle("lib:gen").unwrap().to_vec();
+        cycle.sort();
+        let gen = tasks.index("lib:gen").unwrap();
+        assert_eq!(cycle, [build, gen]);
+        assert_eq!(tasks.cycle("lib:release"), None);
+        let edges = tasks.edges_named().collect::<HashSet<_>>();
+        let edge = |from: &str, to: &str| (from.to_string(), to.to_string());
+        assert_eq!(
+            edges,
+            HashSet::from([
+                edge("lib:gen", "lib:build"),
+                edge("lib:lint", "lib:build"),
+                edge("lib:build", "lib:gen"),
+                edge("lib:build", "lib:release"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_task_lines() {
+        let yaml = indoc! {r#"
+             version: '3'
+             tasks:
+               build:
+                 deps: [gen]
+
+               # generated code
+               "gen": {}
+               'lint:all': # quoted because of the colon
+                 cmds: [lint]
+             ---
+             tasks:
+               build:
+                 deps: [lint:all]
+            "#};
+        let taskfile = parse(yaml.as_bytes()).unwrap();
+        let lines = taskfile
+            .tasks
+            .iter()
+            .map(|t| (t.name.as_str(), t.line))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(lines["build"], Some(12));
+        assert_eq!(lines["gen"], Some(7));
+        assert_eq!(lines["lint:all"], Some(8));
+        let flow = parse("tasks: {build: {}}".as_bytes()).unwrap();
+        assert_eq!(flow.tasks[0].line, None);
+    }
+
+    #[test]
+    fn test_wrap_labels() {
+        assert_eq!(wrap("build the docs site", 9), "build the\ndocs site");
+        assert_eq!(wrap("a superlongword", 4), "a\nsuperlongword");
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               docs:
+                 desc: Build the "docs" site
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            label: Label::Desc,
+            wrap: Some(11),
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains(r#"label = "docs\nBuild the\n\"docs\" site""#));
+        let dot = graph_to_dot(&g, &DotOptions::default());
+        assert!(dot.contains(r#"label = "docs""#));
+    }
+
+    #[test]
+    fn test_degree_labels() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 desc: Ship it
+                 deps: [build, docs]
+               build:
+                 deps: [gen]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            label: Label::Desc,
+            degrees: true,
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot
+            .contains("label = \"release\\nShip it\\n(\u{2193}2 \u{2191}0)\""));
+        assert!(dot.contains("label = \"build\\n(\u{2193}1 \u{2191}1)\""));
+        assert!(dot.contains("label = \"gen\\n(\u{2193}0 \u{2191}1)\""));
+    }
+
+    #[test]
+    fn test_mono_labels() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 desc: Ship "it"
+                 deps: [build]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            label: Label::Desc,
+            mono: true,
+            node_attrs: vec!["fontname=\"Fira Code\"".to_string()],
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains("label = \"release\\lShip \\\"it\\\"\\l\""));
+        assert!(dot.contains("label = \"build\\l\""));
+        assert!(dot
+            .contains("node [ fontname=\"Courier\" fontname=\"Fira Code\" ]"));
+    }
+
+    #[test]
+    fn test_reverse_edges() {
+        let yaml = Cursor::new("tasks:\n  release:\n    deps: [build]\n");
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            reverse_edges: true,
+            theme: Theme::Dark,
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains(r##"edge [ color="#c8c8c8" dir="back" ]"##));
+        // Still from the dependency, for the layout
+        let edge =
+            format!("{} -> {}", n["build"].index(), n["release"].index());
+        assert!(dot.contains(&edge));
+        assert!(!graph_to_dot(&g, &DotOptions::default()).contains("dir="));
+    }
+
+    #[test]
+    fn test_rank_by_namespace() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 deps: [lib:build, lib:docs:html, lib:test, app:build]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            rank_by_namespace: true,
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        let ranks = dot
+            .lines()
+            .filter(|l| l.contains("rank=same"))
+            .collect::<Vec<_>>();
+        assert_eq!(ranks, ["    { rank=same; 1; 3; }"]);
+        assert!(!graph_to_dot(&g, &DotOptions::default()).contains("rank"));
+    }
+
+    #[test]
+    fn test_summary_tooltip() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               build:
+                 desc: Build it
+                 summary: |
+                   Build the binaries.
+                   With "release" settings.
+               docs:
+                 summary: Build the docs.
+               lint:
+                 desc: Lint it
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            label: Label::Desc,
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains(concat!(
+            r#"label = "build\nBuild it" "#,
+            r#"tooltip="Build the binaries.\nWith \"release\" settings.\n""#
+        )));
+        assert!(dot.contains(
+            r#"label = "docs\nBuild the docs." tooltip="Build the docs.""#
+        ));
+        assert!(dot.contains(r#"label = "lint\nLint it" tooltip="Lint it""#));
+    }
+
+    #[test]
+    fn test_namespace_separator() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 deps: ["docs:build"]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        assert!(g.contains_edge(n["docs:build"], n["release"]));
+        let options = DotOptions {
+            separator: Some('/'),
+            clusters: true,
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains(r#"label = "docs/build""#));
+        assert!(dot.contains(r#"subgraph "cluster_docs""#));
+        assert!(!dot.contains("docs:build"));
+    }
+
+    #[test]
+    fn test_write_json() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 desc: Ship "it"
+                 deps: [build]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let mut out = vec![];
+        write_json(&g, &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 2);
+        assert_eq!(json["nodes"][0]["desc"], "Ship \"it\"");
+        assert_eq!(json["nodes"][1]["defined"], false);
+        assert_eq!(json["edges"][0]["from"], "build");
+        assert_eq!(json["edges"][0]["to"], "release");
+    }
+
+    #[test]
+    fn test_read_json() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 desc: Ship "it"
+                 deps: ["lib:build"]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let mut out = vec![];
+        write_json(&g, &mut out).unwrap();
+        let (mut read, mut h) = (HashMap::new(), DiGraph::new());
+        read_json(out.as_slice(), &mut read, &mut h).unwrap();
+        let release = &h[read["release"]];
+        assert!(release.defined);
+        assert_eq!(release.desc.as_deref(), Some("Ship \"it\""));
+        assert!(!h[read["lib:build"]].defined);
+        assert!(h.contains_edge(read["lib:build"], read["release"]));
+        let json =
+            r#"{"nodes": [{"name": "lib:build", "line": 3}], "edges": []}"#;
+        let (mut read, mut h) = (HashMap::new(), DiGraph::new());
+        read_json(json.as_bytes(), &mut read, &mut h).unwrap();
+        let build = &h[read["lib:build"]];
+        assert_eq!((build.local_name(), build.line), ("build", Some(3)));
+        let error = |json: &str| {
+            let (mut n, mut g) = (HashMap::new(), DiGraph::new());
+            format!(
+                "{:#}",
+                read_json(json.as_bytes(), &mut n, &mut g).unwrap_err()
+            )
+        };
+        assert_eq!(
+            error(r#"{"nodes": []}"#),
+            "expected an object with an array `edges`"
+        );
+        assert_eq!(
+            error(r#"{"nodes": [{"name": 1}], "edges": []}"#),
+            "nodes[0]: expected a string for `name`"
+        );
+        assert_eq!(
+            error(
+                r#"{"nodes": [{"name": "a"}], "edges": [{"from": "a", "to": "b"}]}"#
+            ),
+            "edges[0]: no task `b` in `nodes`"
+        );
+    }
+
+    #[test]
+    fn test_distinct_cycles() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               a:
+                 deps: [b]
+               b:
+                 deps: [a]
+               c:
+                 deps: [d, a]
+               d:
+                 deps: [c]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            distinct_cycles: true,
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert_eq!(dot.matches(r#"color="red""#).count(), 4);
+        assert_eq!(dot.matches(r##"color="#1f77b4""##).count(), 4);
+        // The dependency from one cycle to the other is in neither
+        let edge = format!("{} -> {} [ ]", n["a"].index(), n["c"].index());
+        assert!(dot.contains(&edge));
+        let options = DotOptions {
+            palette: vec!["gold".into(), "navy".into()],
+            ..options
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert_eq!(dot.matches(r#"color="gold""#).count(), 4);
+        assert_eq!(dot.matches(r#"color="navy""#).count(), 4);
+        let dot = graph_to_dot(&g, &DotOptions::default());
+        assert_eq!(dot.matches(r#"color="red""#).count(), 8);
+    }
+
+    #[test]
+    fn test_self_dependency_is_a_cycle() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               a:
+                 deps: [a, b]
+               b: {}
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let dot = graph_to_dot(&g, &DotOptions::default());
+        let a = n["a"].index();
+        assert!(dot.contains(&format!("{a} -> {a} [ color=\"red\"]")));
+        assert_eq!(dot.matches(r#"color="red""#).count(), 2);
+        assert_eq!(crate::analysis::cycles(&g).len(), 1);
+    }
+
+    #[test]
+    fn test_cycle_clusters() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               a:
+                 deps: [b]
+               b:
+                 deps: [a]
+               c:
+                 deps: [d, a]
+               d:
+                 deps: [c]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            cycle_clusters: true,
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        let cluster = |i: usize, tasks: [&str; 2]| {
+            format!(
+                "    subgraph cluster_cycle_{i} {{\n        \
+                 label = \"cycle {i}\"\n        color=\"red\"\n        \
+                 {}\n        {}\n    }}\n",
+                n[tasks[0]].index(),
+                n[tasks[1]].index()
+            )
+        };
+        assert!(dot.contains(&cluster(1, ["a", "b"])));
+        assert!(dot.contains(&cluster(2, ["c", "d"])));
+        // Still colored
+        assert_eq!(dot.matches(r#"color="red""#).count(), 10);
+
+        // Within the namespace all the tasks of a cycle are in, and out of
+        // those only some of them are in
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               lib:build:
+                 deps: ["lib:docs:html"]
+               lib:docs:html:
+                 deps: ["lib:build"]
+               lib:docs:pdf:
+                 deps: [release]
+               release:
+                 deps: ["lib:docs:pdf"]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let options = DotOptions {
+            clusters: true,
+            ..options
+        };
+        let dot = graph_to_dot(&g, &options);
+        let lib = dot.find("subgraph \"cluster_lib\"").unwrap();
+        let docs = dot.find("subgraph \"cluster_lib:docs\"").unwrap();
+        let (first, second) = (
+            dot.find("subgraph cluster_cycle_1").unwrap(),
+            dot.find("subgraph cluster_cycle_2").unwrap(),
+        );
+        assert!(lib < first && first < docs);
+        assert!(docs < second);
+        assert!(dot.contains(
+            "        subgraph \"cluster_lib:docs\" {\n            \
+             label = \"lib:docs\"\n        }\n"
+        ));
+        assert!(dot.contains(&format!(
+            "\n    subgraph cluster_cycle_2 {{\n        label = \"cycle 2\"\n\
+             \x20       color=\"red\"\n        {}\n        {}\n    }}\n",
+            n["lib:docs:pdf"].index(),
+            n["release"].index()
+        )));
+    }
+
+    #[test]
+    fn test_name_ids() {
+        let graph = |yaml: &str| {
+            let mut n = HashMap::new();
+            let mut g = DiGraph::new();
+            build_graph(
+                Cursor::new(yaml.to_string()),
+                &[],
+                &mut n,
+                &mut g,
+                true,
+                &HashMap::new(),
+            )
+            .unwrap();
+            g
+        };
+        let g = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: ["lib:build", "lib-build", lib_build]
+            "#});
+        let options = DotOptions {
+            name_ids: true,
+            clusters: true,
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        // Taken in the order of the names: `lib-build`, `lib:build`, and
+        // then `lib_build`, which finds its own name taken
+        assert!(dot.contains("    \"release\" [ label = \"release\"]\n"));
+        assert!(dot.contains("    \"lib_build\" [ label = \"lib-build\""));
+        assert!(dot.contains("    \"lib_build_2\" [ label = \"lib:build\""));
+        assert!(dot.contains("    \"lib_build_3\" [ label = \"lib_build\""));
+        assert!(dot.contains("    \"lib_build_2\" -> \"release\" [ ]\n"));
+        assert!(
+            dot.contains("        label = \"lib\"\n        \"lib_build_2\"\n")
+        );
+        // The same ids for the same tasks read in another order
+        let other = graph(indoc! {r#"
+             tasks:
+               lib_build: {}
+               release:
+                 deps: [lib-build, "lib:build"]
+            "#});
+        let ids = |g: &DiGraph<Node, Dep>| {
+            let mut ids = g
+                .node_indices()
+                .map(|idx| {
+                    (
+                        g[idx].name.clone(),
+                        node_ids(g, &options)[idx.index()].clone(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(&g), ids(&other));
+        assert_eq!(node_ids(&g, &DotOptions::default())[0], "0");
+        // Never the id of a node of a legend
+        let g = graph(indoc! {r#"
+             tasks:
+               release:
+                 deps: [legend-namespaces, legend_0_to, legend_max_to]
+            "#});
+        let options = DotOptions {
+            namespace_legend: true,
+            legend: vec![("silent".into(), "color=\"gray\"".into())],
+            ..options
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains(
+            "\"legend_namespaces_2\" [ label = \"legend-namespaces\""
+        ));
+        assert!(dot.contains("\"legend_0_to_2\" [ label = \"legend_0_to\""));
+        assert!(dot.contains("\"legend_max_to\" [ label = \"legend_max_to\""));
+    }
+
+    #[test]
+    fn test_apply_overlay() {
+        let base = Cursor::new(indoc! {r#"
+             tasks:
+               foo:
+                 deps: [bar, baz]
+               bar:
+                 desc: bar
+            "#});
+        let overlay = Cursor::new(indoc! {r#"
+             tasks:
+               foo:
+                 deps: [bar, spam]
+               spam:
+                 desc: only in the overlay
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(base, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        apply_overlay(overlay, &mut n, &mut g).unwrap();
+        let mut edges = g
+            .edge_indices()
+            .map(|e| {
+                let (a, b) = g.edge_endpoints(e).unwrap();
+                (g[a].name.as_str(), g[b].name.as_str())
+            })
+            .collect::<Vec<_>>();
+        edges.sort();
+        assert_eq!(edges, [("bar", "foo"), ("spam", "foo")]);
+        assert!(!n.contains_key("baz"));
+        assert_eq!(g.node_count(), 3);
+    }
+
+    #[test]
+    fn test_parse_multiple_documents() {
+        let yaml = Cursor::new(indoc! {r#"
+             version: '3'
+             vars:
+               A: a
+             tasks:
+               first:
+                 deps: [second]
+             ---
+             tasks:
+               second:
+                 desc: from the second document
+             ---
+            "#});
+        let taskfile = parse(yaml).unwrap();
+        let names = taskfile
+            .tasks
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["first", "second"]);
+        assert_eq!(taskfile.vars["A"], "a");
+    }
+
+    #[test]
+    fn test_dark_theme() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               a:
+                 deps: [b]
+               b:
+                 deps: [a]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let light = graph_to_dot(&g, &DotOptions::default());
+        assert!(!light.contains("bgcolor"));
+        assert!(light.contains(r#"color="red""#));
+        let options = DotOptions {
+            theme: Theme::Dark,
+            ..Default::default()
+        };
+        let dark = graph_to_dot(&g, &options);
+        let lines = dark.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "digraph {");
+        assert_eq!(lines[1], r##"    graph [ bgcolor="#1e1e1e" ]"##);
+        assert!(lines[2].starts_with("    node [ "));
+        assert!(lines[3].starts_with("    edge [ "));
+        assert!(!dark.contains(r#"color="red""#));
+        assert!(dark.contains(r##"color="#ff6b6b""##));
+        // Dark boxes rather than the background, which may be transparent
+        assert!(lines[2].contains(r##"style="filled" fillcolor="#2d2d2d""##));
+        let yaml = Cursor::new("tasks: {a: {deps: [b]}}");
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let dark = graph_to_dot(&g, &options);
+        assert!(dark.contains(r#"label = "b" style="filled,dashed""#));
+    }
+
+    #[test]
+    fn test_graph_to_dot_with_styles() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               a:
+                 deps: [b]
+               b:
+                 deps: [a, c]
+               c: {}
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let dot = graph_to_dot_with(
+            &g,
+            &DotOptions::default(),
+            |name| {
+                if name == "c" {
+                    r#"shape="box""#.into()
+                } else {
+                    String::new()
+                }
+            },
+            |from, to| {
+                if (from, to) == ("a", "b") {
+                    r#"color="blue""#.into()
+                } else {
+                    String::new()
+                }
+            },
+        );
+        assert!(dot.contains(r#"label = "c" shape="box""#));
+        // The custom color is written last, so it wins over the cycle's red
+        assert!(dot.contains(r#"color="red" color="blue""#));
+    }
+
+    #[test]
+    fn test_check_written() {
+        let broken = || Err(anyhow::anyhow!("couldn't write to stdin"));
+        let e = check_written(broken(), b"Error: syntax error in line 1\n")
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "`dot` failed: Error: syntax error in line 1"
+        );
+        assert_eq!(e.root_cause().to_string(), "couldn't write to stdin");
+        let e = check_written(broken(), b"").unwrap_err();
+        assert_eq!(e.to_string(), "couldn't write to stdin");
+        assert!(check_written(Ok(()), b"Warning: something").is_ok());
+    }
+
+    #[test]
+    fn test_version_supported() {
+        assert!(version_supported("3", "3.38.0"));
+        assert!(version_supported("3.28", "3.38.0"));
+        assert!(version_supported("3.38", "3.38.0"));
+        assert!(!version_supported("3.40", "3.38.0"));
+        assert!(!version_supported("2", "3.38.0"));
+        assert!(!version_supported("3", "2.8.1"));
+        assert!(!version_supported("latest", "3.38.0"));
+        let taskfile = parse("version: 3\ntasks: {}\n".as_bytes()).unwrap();
+        assert_eq!(taskfile.version.as_deref(), Some("3"));
+        let taskfile =
+            parse("version: '3.28'\ntasks: {}\n".as_bytes()).unwrap();
+        assert_eq!(taskfile.version.as_deref(), Some("3.28"));
+    }
+
+    #[test]
+    fn test_check_executable() -> Result<()> {
+        let temp = TempDir::new("taskdep")?;
+        let missing = temp.path().join("dot");
+        assert!(check_executable(&missing).is_err());
+        assert!(check_executable(temp.path()).is_err());
+        File::create(&missing)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let e = check_executable(&missing).unwrap_err();
+            assert!(e.to_string().ends_with(": not executable"));
+            let permissions = std::fs::Permissions::from_mode(0o755);
+            std::fs::set_permissions(&missing, permissions)?;
+        }
+        assert!(check_executable(&missing).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_legend() {
+        let mut g = DiGraph::new();
+        g.add_node(Node::new("a".into()));
+        assert!(!graph_to_dot(&g, &DotOptions::default()).contains("Legend"));
+        let options = DotOptions {
+            legend: vec![("a \"b\"".into(), r#"style="bold""#.into())],
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        let lines = dot.lines().collect::<Vec<_>>();
+        assert_eq!(lines[2], "    subgraph cluster_legend {");
+        assert_eq!(
+            lines[5],
+            r#"        legend_0_from -> legend_0_to [ label="a \"b\"" style="bold" ]"#
+        );
+        assert_eq!(lines[7], "}");
+    }
+
+    #[test]
+    fn test_title() {
+        let mut g = DiGraph::new();
+        g.add_node(Node::new("a".into()));
+        let options = DotOptions {
+            title: Some("Taskfile.yaml\n1 \"task\"".into()),
+            ..Default::default()
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains(
+            r#"graph [ label="Taskfile.yaml\n1 \"task\"" labelloc="t" ]"#
+        ));
+        let options = DotOptions {
+            footer: Some("output: group".into()),
+            ..options
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains(
+            r#"label="Taskfile.yaml\n1 \"task\"\noutput: group" labelloc="t""#
+        ));
+        let options = DotOptions {
+            title: None,
+            ..options
+        };
+        let dot = graph_to_dot(&g, &options);
+        assert!(dot.contains(r#"label="output: group" labelloc="b""#));
+    }
+
+    #[test]
+    fn test_build_graph_from_zip() -> Result<()> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        let files = [
+            (
+                "Taskfile.yaml",
+                "includes:\n  lib: ./lib/Taskfile.yaml\n\
+                 tasks:\n  build:\n    deps: [lib:compile]\n",
+            ),
+            ("lib/Taskfile.yaml", "tasks:\n  compile: {}\n"),
+            (
+                "escape.yaml",
+                "includes:\n  up: ../Taskfile.yaml\ntasks: {}\n",
+            ),
+        ];
+        for (name, contents) in files {
+            zip.start_file(name, SimpleFileOptions::default())?;
+            zip.write_all(contents.as_bytes())?;
+        }
+        let mut source = ZipSource::new(zip.finish()?).unwrap();
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph_from(
+            &mut source,
+            "Taskfile.yaml",
+            &[],
+            &mut n,
+            &mut g,
+            true,
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert!(g[n["lib:compile"]].defined);
+        assert!(g.contains_edge(n["lib:compile"], n["build"]));
+        assert_eq!(g[n["build"]].file.as_deref(), Some("Taskfile.yaml"));
+        assert_eq!(
+            g[n["lib:compile"]].file.as_deref(),
+            Some("./lib/Taskfile.yaml")
+        );
+        let err = build_graph_from(
+            &mut source,
+            "escape.yaml",
+            &[],
+            &mut n,
+            &mut g,
+            true,
+            &HashMap::new(),
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("outside of the archive"));
+        Ok(())
+    }
+}
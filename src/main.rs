@@ -1,166 +1,806 @@
 use anyhow::{anyhow, bail, Context, Result};
-use clap::Parser;
-use petgraph::graph::NodeIndex;
-use petgraph::stable_graph::DefaultIx;
-use petgraph::visit::EdgeRef;
-use petgraph::{
-    algo::tarjan_scc,
-    dot::{Config, Dot},
-    graph::DiGraph,
-};
-use serde_yaml::{self, Value};
-use std::fs::{canonicalize, File};
-use std::process::{Command, Output, Stdio};
-use std::thread;
+use clap::{Parser, ValueEnum};
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::Direction;
+use regex::Regex;
+use std::env;
+use std::fmt;
+use std::fs::{self, canonicalize, File};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
 use std::{
     collections::{HashMap, HashSet},
-    fmt::{self, Debug, Formatter},
-    io::{Read, Write},
+    io::{BufRead, BufReader, BufWriter, ErrorKind, IsTerminal, Read, Write},
+    thread,
+    time::{Duration, Instant},
+};
+use taskdep::a11y::add_labels;
+use taskdep::analysis::{
+    blocked_by_cycles, collapse_leaves, colon_names, condense, cycle_path,
+    cycles, dependency_path, dependency_paths, depths, depths_from,
+    feedback_edges, focus, in_namespace, linear_chains, longest_chain,
+    namespace_deps, namespace_pages, outside_namespaces, plan, reachable,
+    redundant_deps, similar_names, subgraph, top_namespace, topo_groups,
+    unmatched, Stats,
+};
+use taskdep::badge::badge_svg;
+use taskdep::clusters::add_script;
+use taskdep::colors::ColorMap;
+use taskdep::merge::{merge_svgs, page_id};
+use taskdep::provenance::embed_sources;
+use taskdep::schema::Validating;
+use taskdep::serve::serve;
+use taskdep::source::{
+    find_taskfiles, FileSystem, Source, WithFormat, ZipSource,
+};
+use taskdep::{
+    apply_overlay, build_graph_from, dot_to_image_with, escape, heat_color,
+    parse, print_includes, read_json, task_version, version_supported,
+    write_json, Dep, DotOptions, InputFormat, Label, Node, RenderOptions,
+    Styles, TaskGraph, Theme,
 };
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+/// Output formats for the generated graph
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Svg,
+    Png,
+    Pdf,
+    /// DOT source, without running Graphviz
+    Dot,
+    /// The tasks and dependencies as JSON, without running Graphviz
+    Json,
+    /// The JSON output of Graphviz, with the positions it laid out the tasks
+    /// and dependencies at
+    #[clap(alias = "gv-json")]
+    Gvjson,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Pdf => "pdf",
+            Format::Dot => "dot",
+            Format::Json | Format::Gvjson => "json",
+        }
+    }
+
+    /// The name of the format for `dot -T`
+    fn graphviz_name(&self) -> &'static str {
+        match self {
+            Format::Gvjson => "json",
+            _ => self.extension(),
+        }
+    }
+
+    /// Whether the format is an image, for the browser to show, rather than
+    /// data for other tools
+    fn is_image(&self) -> bool {
+        matches!(self, Format::Svg | Format::Png | Format::Pdf)
+    }
+
+    fn parse(s: &str) -> Result<Format> {
+        Format::from_str(s, true).map_err(|_| anyhow!("unknown format `{s}`"))
+    }
+}
+
+/// Forms of the summary written by `--badge`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Badge {
+    /// A shields.io-style SVG badge, written to the output file
+    Svg,
+    /// One line of text, on stdout
+    Text,
+}
+
+/// What `--input -` reads from the standard input
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum StdinFormat {
+    /// A Taskfile, parsed according to `--input-format`
+    #[default]
+    Taskfile,
+    /// A graph, as written by `--format json`
+    Json,
+}
+
+/// Largest size of the rendered image, in inches, from `W,H` or `WxH` with
+/// an optional `!`
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Size {
+    width: f64,
+    height: f64,
+    /// Scale the image up as well as down, to fill the size exactly
+    fill: bool,
+}
+
+impl std::str::FromStr for Size {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid =
+            || anyhow!("expected `WxH` in inches, such as `8x6`, not `{s}`");
+        let (dims, fill) = match s.strip_suffix('!') {
+            Some(dims) => (dims, true),
+            None => (s, false),
+        };
+        let parse = |n: &str| match n.trim().parse::<f64>() {
+            Ok(n) if n > 0.0 && n.is_finite() => Ok(n),
+            _ => Err(invalid()),
+        };
+        let (width, height) =
+            dims.split_once([',', 'x']).ok_or_else(invalid)?;
+        Ok(Size {
+            width: parse(width)?,
+            height: parse(height)?,
+            fill,
+        })
+    }
+}
+
+impl std::fmt::Display for Size {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{},{}", self.width, self.height)?;
+        if self.fill {
+            write!(f, "!")?;
+        }
+        Ok(())
+    }
+}
+
+/// Two tasks, from `FROM..TO`
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Between {
+    from: String,
+    to: String,
+}
+
+impl std::str::FromStr for Between {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once("..") {
+            Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+                Ok(Between {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                })
+            }
+            _ => bail!("expected two tasks as `FROM..TO`, not `{s}`"),
+        }
+    }
+}
+
+/// Graphviz colors, from `C1,C2,...`
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Palette(Vec<String>);
+
+impl std::str::FromStr for Palette {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let colors = s
+            .split(',')
+            .map(str::trim)
+            .filter(|color| !color.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if colors.is_empty() {
+            bail!("expected colors separated by commas, not `{s}`");
+        }
+        Ok(Palette(colors))
+    }
+}
+
+/// Graphviz layout engines
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Engine {
+    Dot,
+    Neato,
+    Fdp,
+    Sfdp,
+    Circo,
+    Twopi,
+}
+
+impl Engine {
+    fn name(&self) -> &'static str {
+        self.to_possible_value()
+            .map(|v| v.get_name())
+            .unwrap_or_default()
+    }
+
+    /// Whether the engine starts from a random layout, which `--seed` fixes
+    fn is_force_directed(&self) -> bool {
+        matches!(self, Engine::Neato | Engine::Fdp | Engine::Sfdp)
+    }
+}
+
+/// Project settings read from `.taskdeprc`.
+///
+/// The file uses a small subset of TOML: one `key = "value"` per line,
+/// with `#` starting a comment.
+#[derive(Debug, Default)]
+struct Settings {
+    format: Option<String>,
+}
+
+fn parse_config<R>(f: R) -> Result<Settings>
+where
+    R: Read,
+{
+    let mut config = Settings::default();
+    for (i, line) in BufReader::new(f).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("line {}: expected `key = value`", i + 1))?;
+        let value = value.trim().trim_matches('"').to_string();
+        match key.trim() {
+            "format" => config.format = Some(value),
+            key => bail!("line {}: unknown key `{key}`", i + 1),
+        }
+    }
+    Ok(config)
+}
 
-struct Node(String);
+fn read_config() -> Result<Settings> {
+    match File::open(".taskdeprc") {
+        Ok(f) => parse_config(f).context(".taskdeprc"),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(anyhow!(".taskdeprc: {e}")),
+    }
+}
 
-impl Debug for Node {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+/// Pick the output format: CLI flag > `TASKDEP_FORMAT` > `.taskdeprc` > SVG.
+fn resolve_format(
+    cli: Option<Format>,
+    env: Option<&str>,
+    config: &Settings,
+) -> Result<Format> {
+    if let Some(format) = cli {
+        return Ok(format);
+    }
+    if let Some(format) = env {
+        return Format::parse(format).context("TASKDEP_FORMAT");
+    }
+    if let Some(format) = &config.format {
+        return Format::parse(format).context(".taskdeprc");
     }
+    Ok(Format::Svg)
 }
 
-fn build_graph<R>(
+/// Read task names, one per line, and find them in the graph. Blank lines and
+/// lines starting with `#` are skipped.
+fn parse_task_list<R>(
     f: R,
-    prefix: &[String],
-    nodes: &mut HashMap<String, NodeIndex<DefaultIx>>,
-    graph: &mut DiGraph<Node, String>,
-) -> Result<()>
+    nodes: &HashMap<String, NodeIndex>,
+    graph: &DiGraph<Node, Dep>,
+) -> Result<Vec<NodeIndex>>
 where
     R: Read,
 {
-    let yaml: HashMap<String, Value> = serde_yaml::from_reader(f)?;
-    if let Some(incs) = yaml.get("includes") {
-        let namespaces = incs
-            .as_mapping()
-            .ok_or_else(|| anyhow!("includes is not a mapping"))?;
-        for (namespace, descr) in namespaces {
-            let name = namespace
-                .as_str()
-                .ok_or_else(|| anyhow!("namespace is not a string"))?;
-            let taskfile = match descr {
-                Value::String(s) => s,
-                Value::Mapping(m) => {
-                    m.get("taskfile").and_then(|t| t.as_str()).ok_or_else(
-                        || anyhow!("couldn't find taskfile name to include"),
-                    )?
-                }
-                _ => bail!("incorrect type for an include"),
-            };
-            let f = File::open(taskfile)?;
-            build_graph(f, &[prefix, &[name.into()]].concat(), nodes, graph)?;
-        }
-    }
-    let tasks = yaml
-        .get("tasks")
-        .ok_or_else(|| anyhow!("tasks not found"))?
-        .as_mapping()
-        .ok_or_else(|| anyhow!("tasks is not a mapping"))?;
-    for (task, descr) in tasks {
-        let name = task
-            .as_str()
-            .ok_or_else(|| anyhow!("task name is not a string"))?;
-        let name = [prefix, &[name.into()]].concat().join(":");
-        nodes
-            .entry(name.clone())
-            .or_insert_with(|| graph.add_node(Node(name.clone())));
-        if let Some(deps) = descr
-            .as_mapping()
-            .ok_or_else(|| anyhow!("task is not a mapping"))?
-            .get("deps")
-        {
-            for dep in deps
-                .as_sequence()
-                .ok_or_else(|| anyhow!("deps is not a list"))?
-            {
-                let dep_name = match dep {
-                    Value::String(n) => n,
-                    Value::Mapping(m) => m
-                        .get("task")
-                        .and_then(|t| t.as_str())
-                        .ok_or_else(|| anyhow!("couldn't find name of task"))?,
-                    _ => bail!("incorrect type for a dependency"),
-                };
-                let full_dep_name =
-                    [prefix, &[dep_name.into()]].concat().join(":");
-                nodes.entry(full_dep_name.clone()).or_insert_with(|| {
-                    graph.add_node(Node(full_dep_name.clone()))
-                });
-                graph.add_edge(
-                    nodes[&full_dep_name],
-                    nodes[&name],
-                    format!("{full_dep_name}-{name}"),
-                );
-            }
+    let mut entrypoints = vec![];
+    for (i, line) in BufReader::new(f).lines().enumerate() {
+        let line = line?;
+        let name = line.trim();
+        if name.is_empty() || name.starts_with('#') {
+            continue;
+        }
+        match nodes.get(name) {
+            Some(&idx) if graph[idx].defined => entrypoints.push(idx),
+            _ => bail!("line {}: unknown task `{name}`", i + 1),
+        }
+    }
+    Ok(entrypoints)
+}
+
+/// Percent-encode the characters of `s` that can't be left as they are in a
+/// URL, or that would end it in a quoted DOT string.
+fn percent_encode(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            ' ' | '"' | '#' | '%' | '<' | '>' => format!("%{:02X}", c as u8),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Expand `{task}` in `template` to the name of the task, `{file}` to the
+/// path of the Taskfile defining it and `{line}` to the line of its
+/// definition, or to nothing when they aren't known. They are
+/// percent-encoded, so that a `#` in a path doesn't start the fragment.
+fn task_link(template: &str, n: &Node) -> String {
+    let file = n
+        .file
+        .as_ref()
+        .map(|f| match canonicalize(f) {
+            Ok(path) => path.to_string_lossy().into_owned(),
+            Err(_) => f.clone(),
+        })
+        .unwrap_or_default();
+    template
+        .replace('"', "%22")
+        .replace("{task}", &percent_encode(&n.name))
+        .replace("{file}", &percent_encode(&file))
+        .replace("{line}", &n.line.map(|l| l.to_string()).unwrap_or_default())
+}
+
+/// Write each cycle as a loop of dependencies, `DEP -> TASK -> ... -> DEP`,
+/// with the other tasks of the cycle, if any, after it.
+fn print_cycles<W>(graph: &DiGraph<Node, Dep>, out: &mut W) -> Result<()>
+where
+    W: WriteColor,
+{
+    let mut cycles = cycles(graph);
+    for cycle in &mut cycles {
+        cycle.sort();
+    }
+    cycles.sort();
+    let mut red = ColorSpec::new();
+    red.set_fg(Some(Color::Red)).set_bold(true);
+    for cycle in cycles {
+        let path = cycle_path(graph, &cycle);
+        let names = path
+            .iter()
+            .map(|&idx| graph[idx].name.as_str())
+            .collect::<Vec<_>>();
+        out.set_color(&red)?;
+        write!(out, "{}", names.join(" -> "))?;
+        out.reset()?;
+        let others = cycle
+            .iter()
+            .filter(|idx| !path.contains(idx))
+            .map(|&idx| graph[idx].name.as_str())
+            .collect::<Vec<_>>();
+        if !others.is_empty() {
+            write!(out, " (with {})", others.join(", "))?;
         }
+        writeln!(out)?;
     }
     Ok(())
 }
 
-fn graph_to_dot(g: &DiGraph<Node, String>) -> String {
-    let components = tarjan_scc(&g);
-    let comps = components
-        .iter()
-        .filter(|c| c.len() > 1)
-        .flatten()
-        .collect::<HashSet<_>>();
-    format!(
-        "{:?}",
-        Dot::with_attr_getters(
-            g,
-            &[Config::EdgeNoLabel],
-            &|_g, e| {
-                if comps.contains(&e.source()) && comps.contains(&e.target()) {
-                    "color=\"red\""
-                } else {
-                    ""
-                }
-                .into()
-            },
-            &|_g, (idx, _n)| {
-                if comps.contains(&idx) {
-                    "color=\"red\""
-                } else {
-                    ""
-                }
-                .into()
-            }
-        )
+/// Find a task by name, suggesting similar names when there is none.
+fn find_task(
+    nodes: &HashMap<String, NodeIndex>,
+    graph: &DiGraph<Node, Dep>,
+    name: &str,
+) -> Result<NodeIndex> {
+    if let Some(&idx) = nodes.get(name) {
+        return Ok(idx);
+    }
+    let similar = similar_names(graph, name);
+    if similar.is_empty() {
+        bail!("unknown task `{name}`");
+    }
+    bail!(
+        "unknown task `{name}`; did you mean `{}`?",
+        similar.join("`, `")
     )
 }
 
-fn graph_to_image(g: &DiGraph<Node, String>) -> Result<Output> {
-    let contents = graph_to_dot(g);
-    let dot_command = option_env!("DOTPATH").unwrap_or("dot");
-    let mut dot = Command::new(dot_command)
-        .arg("-Tsvg")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .context("command `dot` not found (please, make sure `graphviz` is installed)")?;
-    let mut stdin = dot
-        .stdin
-        .take()
-        .ok_or_else(|| anyhow!("couldn't open stdin"))?;
-    let stdin_write = thread::spawn(move || {
-        stdin
-            .write_all(contents.as_bytes())
-            .with_context(|| "couldn't write to stdin")
-    });
-    let run_dot = thread::spawn(move || {
-        dot.wait_with_output().with_context(|| "couldn't run `dot`")
-    });
-    stdin_write
-        .join()
-        .map_err(|e| anyhow!("stdin_write: {e:?}"))??;
-    run_dot.join().map_err(|e| anyhow!("run_dot: {e:?}"))?
+/// Like [`find_task`], in a graph rewritten since the tasks were read.
+fn task_in(graph: &DiGraph<Node, Dep>, name: &str) -> Result<NodeIndex> {
+    let current = graph
+        .node_indices()
+        .map(|idx| (graph[idx].name.clone(), idx))
+        .collect::<HashMap<_, _>>();
+    find_task(&current, graph, name)
+}
+
+/// Describe a task in a few lines: what it does, what it depends on, directly
+/// or not, whether it is part of a cycle, and which tasks depend on it.
+fn explain<W>(tasks: &TaskGraph, name: &str, out: &mut W) -> Result<()>
+where
+    W: Write,
+{
+    let (Some(idx), Some(neighbors)) =
+        (tasks.index(name), tasks.neighbors(name))
+    else {
+        bail!("unknown task `{name}`");
+    };
+    let graph = tasks.graph();
+    let n = &graph[idx];
+    let list = |names: &[String]| match names.is_empty() {
+        true => "nothing".to_string(),
+        false => names.join(", "),
+    };
+    writeln!(out, "{}", n.name)?;
+    if let Some(desc) = &n.desc {
+        writeln!(out, "  {desc}")?;
+    }
+    match (&n.file, n.line) {
+        (Some(file), Some(line)) => {
+            writeln!(out, "  defined in {file}:{line}")?
+        }
+        (Some(file), None) => writeln!(out, "  defined in {file}")?,
+        _ if !n.defined => writeln!(out, "  not defined in any Taskfile")?,
+        _ => {}
+    }
+    writeln!(out, "  depends on: {}", list(&neighbors.deps))?;
+    let all = reachable(graph, &[idx]).len() - 1;
+    writeln!(out, "  dependencies in all, directly or not: {all}")?;
+    if let Some(cycle) = tasks.cycle(name) {
+        let mut others = cycle
+            .iter()
+            .filter(|&&i| i != idx)
+            .map(|&i| graph[i].name.clone())
+            .collect::<Vec<_>>();
+        others.sort();
+        match others.is_empty() {
+            true => writeln!(out, "  in a cycle: depends on itself")?,
+            false => writeln!(out, "  in a cycle with: {}", list(&others))?,
+        }
+    } else {
+        writeln!(out, "  not in a cycle")?;
+    }
+    writeln!(out, "  needed by: {}", list(&neighbors.dependents))?;
+    Ok(())
+}
+
+/// Warn when the `task` command is missing, or doesn't support the version
+/// declared by the Taskfile at `path`.
+fn check_task_version(
+    source: &mut dyn Source,
+    path: &str,
+    outcome: &mut Outcome,
+) -> Result<()> {
+    if path == "-" {
+        let message = "--version-check needs a Taskfile read from a file";
+        outcome.warn("not-applicable", None, message.into());
+        return Ok(());
+    }
+    let root = parse(source.open(path)?).context(path.to_string())?;
+    let Some(version) = root.version else {
+        return Ok(());
+    };
+    match task_version() {
+        Ok(installed) if !version_supported(&version, &installed) => {
+            let message = format!(
+                "{path} is written for Task {version}, which Task {installed} \
+                 doesn't support"
+            );
+            outcome.warn("task-version", None, message);
+        }
+        Ok(_) => {}
+        Err(e) => {
+            let message = format!("can't check the version of Task: {e:#}");
+            outcome.warn("task-version", None, message);
+        }
+    }
+    Ok(())
+}
+
+/// Fail unless every dependency of a task in `order` appears before it, and
+/// every task of the graph is in it.
+fn check_order(graph: &DiGraph<Node, Dep>, order: &[NodeIndex]) -> Result<()> {
+    let position = order
+        .iter()
+        .enumerate()
+        .map(|(i, &idx)| (idx, i))
+        .collect::<HashMap<_, _>>();
+    for (i, &idx) in order.iter().enumerate() {
+        let mut deps = graph
+            .neighbors_directed(idx, Direction::Incoming)
+            .filter(|dep| position.get(dep).is_some_and(|&p| p >= i))
+            .collect::<Vec<_>>();
+        deps.sort_by_key(|dep| position[dep]);
+        if let Some(dep) = deps.first() {
+            bail!(
+                "task `{}` comes before its dependency `{}`",
+                graph[idx].name,
+                graph[*dep].name
+            );
+        }
+    }
+    let mut missing = graph
+        .node_indices()
+        .filter(|idx| graph[*idx].defined && !position.contains_key(idx))
+        .map(|idx| graph[idx].name.as_str())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        missing.sort();
+        bail!("tasks missing from the order: {}", missing.join(", "));
+    }
+    Ok(())
+}
+
+/// Fail if a chain of dependencies is longer than `max`, or if there is a
+/// cycle, making chains endless.
+fn check_max_depth(graph: &DiGraph<Node, Dep>, max: usize) -> Result<()> {
+    let names = |tasks: &[NodeIndex]| {
+        tasks
+            .iter()
+            .map(|&idx| graph[idx].name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if let Some(mut cycle) = cycles(graph).into_iter().next() {
+        cycle.sort();
+        bail!(
+            "dependency chains are endless, with cycle: {}",
+            names(&cycle)
+        );
+    }
+    let chain = longest_chain(graph);
+    let depth = chain.len().saturating_sub(1);
+    if depth > max {
+        bail!(
+            "dependency chain of depth {depth} exceeds {max}: {}",
+            names(&chain)
+        );
+    }
+    Ok(())
+}
+
+/// Leaves the descriptions of the tasks out of `graph`, and so out of every
+/// output made from it.
+fn redact(graph: &mut DiGraph<Node, Dep>) {
+    for n in graph.node_weights_mut() {
+        n.desc = None;
+        n.summary = None;
+    }
+}
+
+/// Drops the dependencies listed again, such as by a task that several
+/// roots define alike, keeping the first of each.
+fn dedup_deps(graph: &mut DiGraph<Node, Dep>) {
+    let mut seen = HashSet::new();
+    let deps = graph
+        .edge_indices()
+        .filter_map(|e| {
+            let (from, to) = graph.edge_endpoints(e)?;
+            seen.insert((from, to)).then_some((from, to, graph[e]))
+        })
+        .collect::<Vec<_>>();
+    graph.clear_edges();
+    for (from, to, dep) in deps {
+        graph.add_edge(from, to, dep);
+    }
+}
+
+/// The top-level tasks of `graph`: `entrypoints` if given, or else the tasks
+/// that nothing depends on.
+fn top_level(
+    graph: &DiGraph<Node, Dep>,
+    entrypoints: Option<&[NodeIndex]>,
+) -> Vec<NodeIndex> {
+    match entrypoints {
+        Some(entrypoints) => entrypoints.to_vec(),
+        None => graph.externals(Direction::Outgoing).collect::<Vec<_>>(),
+    }
+}
+
+/// Points `entrypoints` to where `moved` moved them in a rewritten graph,
+/// dropping those it left out.
+fn remap(
+    entrypoints: &mut Option<Vec<NodeIndex>>,
+    moved: &HashMap<NodeIndex, NodeIndex>,
+) {
+    if let Some(entrypoints) = entrypoints {
+        *entrypoints = entrypoints
+            .iter()
+            .filter_map(|idx| moved.get(idx))
+            .copied()
+            .collect();
+        // Merged tasks can leave several on the same one
+        entrypoints.sort();
+        entrypoints.dedup();
+    }
+}
+
+/// Canonical paths of the files changed since the git revision `since`, in
+/// the repository of the current directory, or `None` outside of a git
+/// repository.
+fn changed_files(since: &str) -> Result<Option<Vec<PathBuf>>> {
+    let git = |args: &[&str]| -> Result<String> {
+        let output = Command::new("git")
+            .args(args)
+            // For the message telling that this isn't a git repository
+            .env("LC_ALL", "C")
+            .output()
+            .map_err(|e| anyhow!("can't run git: {e}"))?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let root = match git(&["rev-parse", "--show-toplevel"]) {
+        Err(e) if e.to_string().contains("not a git repository") => {
+            return Ok(None)
+        }
+        root => root?,
+    };
+    let root = Path::new(root.trim());
+    let changed = git(&["diff", "--name-only", since, "--"])?;
+    // Deleted files can't be canonicalized, but define no tasks either
+    Ok(Some(
+        changed
+            .lines()
+            .filter_map(|line| canonicalize(root.join(line)).ok())
+            .collect(),
+    ))
+}
+
+/// The files of `paths`, all inside `dir`, that git ignores.
+fn ignored_by_git(dir: &Path, paths: &[PathBuf]) -> Result<HashSet<PathBuf>> {
+    let relative = paths
+        .iter()
+        .filter_map(|p| p.strip_prefix(dir).ok())
+        .collect::<Vec<_>>();
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["check-ignore", "--"])
+        .args(&relative)
+        .output()
+        .map_err(|e| anyhow!("can't run git: {e}"))?;
+    // Exits with 1 when none of the paths is ignored
+    if !matches!(output.status.code(), Some(0 | 1)) {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| dir.join(line))
+        .collect())
+}
+
+/// The file of the page of `namespace` with `--split-by-namespace`: `output`
+/// itself for the tasks outside of any namespace, and `STEM.NAMESPACE.EXT`
+/// next to it for the others, or `NAMESPACE.EXT` when the pages have a
+/// directory of their own.
+fn page_file(output: &str, namespace: &str, own_dir: bool) -> PathBuf {
+    let path = Path::new(output);
+    if namespace.is_empty() {
+        return path.to_path_buf();
+    }
+    if own_dir {
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        return path.with_file_name(format!("{namespace}.{ext}"));
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}.{namespace}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{namespace}"),
+    };
+    path.with_file_name(name)
+}
+
+/// Fail when two of the files to write, each with what it is for, are the
+/// same, as one would silently replace the other: the page of a namespace
+/// `index` is the page for the tasks outside of any namespace with `-o out/`.
+fn check_distinct(files: &[(PathBuf, String)]) -> Result<()> {
+    for (i, (file, what)) in files.iter().enumerate() {
+        if let Some((_, other)) = files[..i].iter().find(|(f, _)| f == file) {
+            bail!(
+                "{other} and {what} would both be written to {}",
+                file.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The Taskfiles given with `--input`, each with the namespace of its tasks:
+/// none, or with `by_filename` the stem of its file name, such as `ci` for
+/// `ci.yaml`. Taskfiles named like the default one, such as
+/// `api/Taskfile.yaml`, are named after their directory instead.
+fn input_roots(
+    inputs: &[String],
+    by_filename: bool,
+) -> Vec<(String, Vec<String>)> {
+    let namespace = |input: &str| {
+        let path = Path::new(input);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        if !stem.eq_ignore_ascii_case("taskfile") {
+            return stem.into_owned();
+        }
+        canonicalize(path)
+            .ok()
+            .and_then(|p| Some(p.parent()?.file_name()?.to_owned()))
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_else(|| stem.into_owned())
+    };
+    inputs
+        .iter()
+        .map(|input| match by_filename {
+            true => (input.clone(), vec![namespace(input)]),
+            false => (input.clone(), vec![]),
+        })
+        .collect()
+}
+
+/// Where a task is defined, as `FILE:LINE: ` to start a warning with, or
+/// an empty string when that isn't known.
+fn location(n: &Node) -> String {
+    match (&n.file, n.line) {
+        (Some(file), Some(line)) => format!("{file}:{line}: "),
+        (Some(file), None) => format!("{file}: "),
+        _ => String::new(),
+    }
+}
+
+/// Whether `path` is the same file as one of the canonical `paths`.
+fn is_any_of(path: &Path, paths: &[PathBuf]) -> bool {
+    let canonical = match canonicalize(path) {
+        Ok(p) => p,
+        // The file doesn't exist yet, so it can't be the same as another
+        Err(_) => return false,
+    };
+    paths.contains(&canonical)
+}
+
+/// The contents of the output file for `g` with `styles`, timing its DOT
+/// source and its rendering for `--profile`. The DOT source is also written
+/// to `dot_copy`, if any.
+fn draw(
+    styles: &Styles,
+    g: &DiGraph<Node, Dep>,
+    options: &DotOptions,
+    format: Format,
+    args: &Args,
+    outcome: &mut Outcome,
+    dot_copy: Option<&str>,
+) -> Result<Vec<u8>> {
+    let started = Instant::now();
+    let cycles = cycles(g);
+    outcome.phase("finding cycles", started);
+    let started = Instant::now();
+    let dot = styles.to_dot_with_cycles(g, options, cycles);
+    outcome.phase("generating DOT", started);
+    if let Some(path) = dot_copy {
+        File::create(path)
+            .and_then(|mut f| f.write_all(dot.as_bytes()))
+            .map_err(|e| anyhow!("{path}: {e}"))?;
+    }
+    let started = Instant::now();
+    let contents = render(dot, format, args);
+    outcome.phase("running dot", started);
+    contents.map_err(|e| Failure::Render.of(e))
+}
+
+/// The contents of the output file for the DOT source `dot`, rendered with
+/// Graphviz unless the output format is DOT itself.
+fn render(dot: String, format: Format, args: &Args) -> Result<Vec<u8>> {
+    if format == Format::Dot {
+        return Ok(dot.into_bytes());
+    }
+    let dot_args = [format!("-K{}", args.engine.name())];
+    let render = RenderOptions {
+        timeout: args.render_timeout.map(Duration::from_secs),
+        progress: !args.silent && std::io::stderr().is_terminal(),
+        dot: args
+            .dot_path
+            .clone()
+            .or_else(|| env::var_os("TASKDEP_DOT").map(PathBuf::from)),
+    };
+    let image =
+        dot_to_image_with(dot, format.graphviz_name(), &dot_args, &render)?;
+    if !image.status.success() {
+        let stderr = String::from_utf8_lossy(&image.stderr);
+        match stderr.trim() {
+            "" => bail!("failed to create image: {}", image.status),
+            stderr => {
+                bail!("failed to create image: {}\n{stderr}", image.status)
+            }
+        }
+    }
+    if args.interactive_clusters && format == Format::Svg {
+        Ok(add_script(&image.stdout))
+    } else {
+        Ok(image.stdout)
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -170,124 +810,2464 @@ fn graph_to_image(g: &DiGraph<Node, String>) -> Result<Output> {
 /// Display Taskfile dependency graph
 ///
 /// Consume `Taskfile.yaml` and generate `Taskfile.svg` showing the dependency graph.
-/// Cycles in the graph show in color red.
+/// With `--input`, the image is named after the input file.
+/// Cycles in the graph show in color red, and dependencies on tasks that are
+/// not defined anywhere show dashed.
+///
+/// The output format defaults to the `TASKDEP_FORMAT` environment variable,
+/// then to `format = "..."` in a `.taskdeprc` file, then to SVG.
 struct Args {
-    /// Do not open browser with the image file
+    /// Taskfile to read, or `ARCHIVE.zip!PATH` for a Taskfile inside a zip
+    /// archive, whose includes are then read from the same archive
+    ///
+    /// Can be repeated to combine the tasks of several Taskfiles in one
+    /// graph, except for archives. `-` reads from the standard input.
+    #[clap(short, long, default_value = "Taskfile.yaml")]
+    input: Vec<String>,
+
+    /// Put the tasks of each `--input` Taskfile, even of a single one, in a
+    /// namespace named after its file, such as `ci` for `ci.yaml`, or after its
+    /// directory for a `Taskfile.yaml`, such as `api` for `api/Taskfile.yaml`
+    #[clap(long, action, conflicts_with = "dir")]
+    namespace_by_filename: bool,
+
+    /// Read every Taskfile in this directory and its subdirectories instead,
+    /// each in the namespace of its directory, such as `services:api`
+    #[clap(long, value_name = "PATH", conflicts_with = "input")]
+    dir: Option<String>,
+
+    /// With `--dir`, skip the Taskfiles that git ignores
+    #[clap(long, action, requires = "dir")]
+    gitignore: bool,
+
+    /// How to parse the Taskfile given with `--input`
+    ///
+    /// Its includes are still parsed according to their own extension.
+    #[clap(long, value_enum, default_value_t)]
+    input_format: InputFormat,
+
+    /// What `--input -` reads from the standard input
+    #[clap(long, value_enum, default_value_t)]
+    stdin_format: StdinFormat,
+
+    /// File to write the graph to [default: the input name, with the
+    /// extension of the format]
+    #[clap(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Also write the DOT source of the graph to PATH, as given to Graphviz
+    /// for the output file
+    #[clap(long, value_name = "PATH")]
+    also_dot: Option<String>,
+
+    /// Do not open browser with the image file, nor show progress while
+    /// rendering it
+    ///
+    /// This is the default when neither stdout nor stderr is a terminal,
+    /// such as in CI, unless `--open` is given.
     #[clap(short, long, action)]
     silent: bool,
-}
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    let taskfile = File::open("Taskfile.yaml")
-        .map_err(|e| anyhow!("Taskfile.yaml: {e}"))?;
-    let mut nodes = HashMap::new();
-    let mut graph: DiGraph<Node, _> = DiGraph::new();
-    build_graph(taskfile, &[], &mut nodes, &mut graph)?;
-    let image = graph_to_image(&graph)?;
-    if !image.status.success() {
-        bail!("failed to create image: {}", image.status);
-    }
-    let mut image_file = File::create("Taskfile.svg")?;
-    image_file.write_all(&image.stdout)?;
-    if !args.silent {
-        let taskfile = canonicalize("Taskfile.svg")?;
-        let url = format!("file://{}", taskfile.to_string_lossy());
-        webbrowser::open(&url)?;
-    }
-    Ok(())
-}
+    /// Open the image file in the browser even when not run from a terminal
+    ///
+    /// The DOT and JSON outputs are never opened, as they aren't images.
+    #[clap(long, action, conflicts_with = "silent")]
+    open: bool,
 
-#[cfg(test)]
-mod test {
-    use crate::{build_graph, graph_to_image};
-    use indoc::{formatdoc, indoc};
-    use petgraph::prelude::DiGraph;
-    use std::{
-        collections::HashMap,
-        fs::File,
-        io::{Cursor, Result, Write},
-    };
-    use tempdir::TempDir;
+    /// Also write the warnings, and the error if any, to FILE as a JSON
+    /// array of objects with their `severity`, `code`, `message`, and the
+    /// `task`, `file` and `line` they're about
+    #[clap(long, value_name = "FILE")]
+    warnings_json: Option<String>,
 
-    #[test]
-    fn test_build_graph() -> Result<()> {
-        let yaml = Cursor::new(String::from(indoc! {r#"
-             foo: 1
-             tasks:
-               foo:
-                 desc: desc
-                 deps:
-                   - bar
-                   - baz
-               bar:
-                 deps:
-                   - task: spam
-                     params: params
-               baz:
-                 deps:
-                   - spam
-               spam:
-                 desc: spam
-               eggs:
-                 desc: no deps
-            "#}));
-        let mut n = HashMap::new();
-        let mut g = DiGraph::new();
-        build_graph(yaml, &["foo".into()], &mut n, &mut g).unwrap();
-        assert_eq!(g.node_count(), 5);
-        assert_eq!(g.edge_count(), 4);
-        Ok(())
-    }
+    /// Print the time spent parsing the Taskfiles and building the graph,
+    /// rewriting it as asked by other options, finding cycles, generating
+    /// DOT, and running `dot` on stderr
+    #[clap(long, action)]
+    profile: bool,
 
-    #[test]
-    fn test_build_graph_with_includes() -> Result<()> {
-        let inc1 = indoc! {r#"
-            tasks:
-              task1_inc1:
-                deps:
-                    - task2_inc1
-              task2_inc1:
-                descr: descr
-        "#};
-        let inc2 = indoc! {r#"
-            tasks:
-              task1_inc2:
-                foo: 1
-        "#};
-        let temp = TempDir::new("taskdep")?;
-        let inc1_filename = temp.path().join("inc1.yaml");
-        let inc2_filename = temp.path().join("inc2.yaml");
-        let mut inc1_file = File::create(&inc1_filename)?;
-        write!(&mut inc1_file, "{}", inc1)?;
-        let mut inc2_file = File::create(&inc2_filename)?;
-        write!(&mut inc2_file, "{}", inc2)?;
-        let yaml = Cursor::new(String::from(formatdoc! {r#"
-             foo: 1
-             includes:
-               inc1: {f1}
-               inc2:
-                 taskfile: {f2}
-             tasks:
-               foo:
-                 deps:
-                   - bar
-                   - baz
-                   - inc1:task1_inc1
-                   - inc2:task1_inc2
-            "#, 
-            f1 = inc1_filename.to_string_lossy(),
-            f2 = inc2_filename.to_string_lossy()
-        }));
-        let mut n = HashMap::new();
-        let mut g = DiGraph::new();
-        build_graph(yaml, &[], &mut n, &mut g).unwrap();
-        let i = graph_to_image(&g).unwrap();
-        let out_filename = temp.path().join("out.svg");
-        let mut out = File::create(out_filename)?;
-        out.write_all(&i.stdout)?;
-        assert_eq!(g.node_count(), 6);
-        assert_eq!(g.edge_count(), 5);
-        Ok(())
+    /// Render again whenever one of the Taskfiles changes, until interrupted
+    #[clap(long, action)]
+    watch: bool,
+
+    /// Serve the SVG image at http://localhost:PORT, on a page that reloads
+    /// it whenever it's rendered again
+    #[clap(long, action, requires = "watch")]
+    serve: bool,
+
+    /// Port to serve the image on
+    #[clap(
+        long,
+        value_name = "PORT",
+        default_value_t = 8000,
+        requires = "serve"
+    )]
+    port: u16,
+
+    /// Output format
+    #[clap(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// Render only the root Taskfile, skipping its `includes`
+    #[clap(long, action)]
+    no_includes: bool,
+
+    /// Check the Taskfile, and the Taskfiles it includes, against a JSON
+    /// Schema of Task v3 before building the graph
+    ///
+    /// This is stricter than the graph itself, which ignores the keys it
+    /// doesn't use, and reports unknown keys and values of the wrong type.
+    #[clap(long, action)]
+    schema: bool,
+
+    /// Print the tree of included Taskfiles instead of rendering the graph
+    #[clap(long, action)]
+    print_includes: bool,
+
+    /// What to show in the node labels
+    #[clap(long, value_enum, default_value_t)]
+    label: Label,
+
+    /// End the label of each task with `(↓IN ↑OUT)`, its numbers of
+    /// dependencies and of tasks depending on it
+    #[clap(long, action)]
+    degree_labels: bool,
+
+    /// Write task labels in a monospace font, with their lines aligned on
+    /// the left
+    #[clap(long, action)]
+    mono: bool,
+
+    /// Draw the arrows from each task to its dependencies, instead of from
+    /// the dependencies to the tasks depending on them
+    ///
+    /// Only the arrows change: the layout, and what taskdep prints, stay the
+    /// same.
+    #[clap(long, action)]
+    reverse_edges: bool,
+
+    /// With `--format dot`, identify the tasks by their names, such as
+    /// `"lib_build"` for `lib:build`, rather than by numbers that change
+    /// whenever tasks are added or removed
+    #[clap(long, action)]
+    name_ids: bool,
+
+    /// Wrap node labels at word boundaries near WIDTH characters
+    #[clap(long, value_name = "WIDTH")]
+    wrap_labels: Option<usize>,
+
+    /// When a Taskfile can't be read, still render the tasks read before it,
+    /// and then exit with the error
+    #[clap(long, action)]
+    render_on_error: bool,
+
+    /// Exit with an error on dependencies on a namespace rather than on one
+    /// of its tasks, instead of warning about them, and once the graph is
+    /// rendered, on cycles and on tasks depended on without being defined
+    ///
+    /// The exit status is 2 for cycles, or else 3 for undefined tasks and
+    /// namespaces, as without `--strict`, which only adds an error message
+    /// and the dependencies on namespaces.
+    #[clap(long, action)]
+    strict: bool,
+
+    /// Override the deps of the tasks redefined in this Taskfile
+    #[clap(long, value_name = "FILE")]
+    overlay: Option<String>,
+
+    /// Color tasks as given by this YAML or JSON file, mapping task names, or
+    /// glob patterns such as `lib:*`, to colors
+    ///
+    /// A color can also be a mapping with the `color` of the outline and the
+    /// `fill` of the box. Tasks of cycles keep the color of their cycle.
+    #[clap(long, value_name = "FILE")]
+    colors: Option<String>,
+
+    /// With `--colors`, color the tasks of cycles as given too
+    #[clap(long, action, requires = "colors")]
+    colors_override_cycles: bool,
+
+    /// Print each cycle as a loop of dependencies, `DEP -> TASK -> ...`,
+    /// instead of rendering the graph
+    #[clap(long, action)]
+    list_cycles: bool,
+
+    /// Print the names of all the tasks, sorted, one per line, instead of
+    /// rendering the graph
+    ///
+    /// Tasks only depended on, without being defined, are listed too.
+    #[clap(long, action)]
+    list_tasks: bool,
+
+    /// With `--list-tasks`, leave out the tasks depended on without being
+    /// defined
+    #[clap(long, action, requires = "list-tasks")]
+    only_defined: bool,
+
+    /// Print the chains of at least N tasks that don't branch, where each
+    /// task is the only dependency of the next one and the only task
+    /// depending on the previous one, instead of rendering the graph
+    ///
+    /// Such chains can be a sign of tasks split further than needed. Tasks
+    /// of cycles are left out.
+    #[clap(long, value_name = "N")]
+    long_chains: Option<usize>,
+
+    /// Never color the text printed to the terminal
+    ///
+    /// Colors are also left out when the output isn't a terminal, or when
+    /// the `NO_COLOR` environment variable is set.
+    #[clap(long, action)]
+    no_color: bool,
+
+    /// Print, for each cycle, dependencies to remove to break it, instead of
+    /// rendering the graph
+    ///
+    /// Dependencies are written as edges of the graph, `DEP -> TASK`. They are
+    /// the back edges of a depth-first search within the cycle: removing them
+    /// is enough, though not always the fewest changes possible.
+    #[clap(long, action)]
+    suggest_fixes: bool,
+
+    /// Print the dependencies implied by others, which can be removed,
+    /// instead of rendering the graph
+    ///
+    /// Each one is written as an edge of the graph, `DEP -> TASK`, followed
+    /// by the tasks through which the task depends on DEP anyway.
+    #[clap(long, action)]
+    find_redundant_deps: bool,
+
+    /// Describe a task, its dependencies and the tasks depending on it,
+    /// instead of rendering the graph
+    #[clap(long, value_name = "TASK")]
+    explain: Option<String>,
+
+    /// Print the tasks Task runs for this one, each after its dependencies,
+    /// instead of rendering the graph
+    #[clap(long, value_name = "TASK")]
+    plan: Option<String>,
+
+    /// Print all the tasks, each after its dependencies, instead of
+    /// rendering the graph
+    ///
+    /// The tasks of each cycle come together, as `[cycle: A, B]`, since
+    /// neither can come after the other.
+    #[clap(long, action)]
+    topo: bool,
+
+    /// Print each cycle, as `[cycle: A, B]`, followed by the tasks depending
+    /// on it, which Task can't run until it is broken, instead of rendering
+    /// the graph
+    ///
+    /// The cycles blocking the most tasks come first.
+    #[clap(long, action)]
+    blocked_by_cycles: bool,
+
+    /// Summarize the graph as `12 tasks • 1 cycle` for a README, instead of
+    /// rendering it
+    ///
+    /// The SVG badge is written to the output file, `Taskfile.badge.svg` by
+    /// default, without running Graphviz.
+    #[clap(long, value_enum, value_name = "FORM")]
+    badge: Option<Badge>,
+
+    /// Show only this task, the tasks it depends on and the tasks depending on
+    /// it, directly or not
+    #[clap(long, value_name = "TASK")]
+    focus: Option<String>,
+
+    /// Highlight how task FROM depends on task TO, directly or not, through
+    /// the fewest tasks
+    #[clap(long, value_name = "FROM..TO")]
+    path: Option<Between>,
+
+    /// Print every way task FROM depends on task TO, directly or not, without
+    /// going through a task twice, instead of rendering the graph
+    ///
+    /// Each one is written as a path of the graph, `TO -> ... -> FROM`.
+    #[clap(long, value_name = "FROM..TO")]
+    all_paths: Option<Between>,
+
+    /// With `--all-paths`, stop after this many paths
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = 100,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        requires = "all-paths"
+    )]
+    max_paths: usize,
+
+    /// With `--path`, gray out the other tasks and dependencies
+    #[clap(long, action, requires = "path")]
+    dim_others: bool,
+
+    /// With `--focus`, show only the direct dependencies of the task and the
+    /// tasks depending on it directly
+    #[clap(long, action, requires = "focus")]
+    direct_only: bool,
+
+    /// Show only the tasks defined in the Taskfiles changed since this git
+    /// revision, and the tasks next to them, which are drawn bold
+    ///
+    /// Without a git repository, every task is shown.
+    #[clap(long, value_name = "REF")]
+    since: Option<String>,
+
+    /// Exit with an error unless FILE lists every task, one per line, after
+    /// all of its dependencies, instead of rendering the graph
+    #[clap(long, value_name = "FILE")]
+    compare_order: Option<String>,
+
+    /// Merge the tasks of each cycle into a single task, showing the graph
+    /// without its cycles
+    #[clap(long, action)]
+    condense: bool,
+
+    /// With `--condense`, also render each cycle on its own, next to the
+    /// output file as `STEM.cycleN.svg`, and link the merged task to it
+    #[clap(long, action, requires = "condense")]
+    #[clap(conflicts_with = "split-by-namespace")]
+    drill_down: bool,
+
+    /// Leave out the tasks whose full name, such as `lib:test`, matches this
+    /// regular expression, with their dependencies
+    ///
+    /// The expression matches anywhere in the name unless anchored, as in
+    /// `:test$`. Tasks are left out after `--focus` picks the tasks to show,
+    /// and before `--exclude-namespace` and `--no-phantom`.
+    #[clap(long, value_name = "REGEX")]
+    prune_regex: Option<Regex>,
+
+    /// Leave out the tasks of this namespace, and of the namespaces nested in
+    /// it, with their dependencies; can be repeated
+    #[clap(long, value_name = "NAMESPACE")]
+    exclude_namespace: Vec<String>,
+
+    /// Leave out the dependencies that aren't defined in any Taskfile,
+    /// instead of drawing them dashed, with a warning for each
+    #[clap(long, action)]
+    no_phantom: bool,
+
+    /// Merge each task without dependencies into the task depending on it,
+    /// when there is only one, listing it in the label of that task
+    #[clap(long, action)]
+    collapse_leaves: bool,
+
+    /// Exit with an error if a chain of dependencies is longer than N, or if
+    /// there is a cycle, instead of rendering the graph
+    ///
+    /// The depth of a chain is its number of dependencies: a task depending
+    /// on another that depends on a third is a chain of depth 2.
+    #[clap(long, value_name = "N")]
+    assert_max_depth: Option<usize>,
+
+    /// Fill tasks on a gradient by their depth, the longest distance from a
+    /// task that nothing depends on: yellow for those, blue for the deepest
+    #[clap(long, action)]
+    heatmap: bool,
+
+    /// File listing the tasks that are run directly, one per line, to use as
+    /// the top-level tasks for `--heatmap`, `--reachable-from` and
+    /// `--unreachable`, instead of those that nothing depends on
+    #[clap(long, value_name = "FILE")]
+    entrypoints: Option<String>,
+
+    /// Print the tasks that none of the top-level tasks depend on, directly
+    /// or not, instead of rendering the graph
+    ///
+    /// Without `--entrypoints`, those are only the tasks of cycles that no
+    /// other task depends on.
+    #[clap(long, action)]
+    unreachable: bool,
+
+    /// Show only these tasks and the tasks they depend on, directly or not,
+    /// or, without a task, those of the top-level tasks
+    #[clap(long, value_name = "TASK", min_values = 0)]
+    reachable_from: Option<Vec<String>>,
+
+    /// Make each task a link to this URL, in which `{task}` is replaced with
+    /// the name of the task, `{file}` with the path of its Taskfile and
+    /// `{line}` with the line defining it
+    #[clap(long, value_name = "TEMPLATE")]
+    link_template: Option<String>,
+
+    /// Draw each namespace as a box around its tasks
+    #[clap(long, action)]
+    cluster: bool,
+
+    /// Like `--cluster`, and let the clusters of an SVG be collapsed and
+    /// expanded by clicking their label
+    #[clap(long, action)]
+    interactive_clusters: bool,
+
+    /// With `--cluster`, color the clusters, and list the namespaces with
+    /// their colors and numbers of tasks in a legend
+    #[clap(long, action)]
+    legend: bool,
+
+    /// Put the tasks of each namespace side by side, in a band across the
+    /// graph, for a layout that reads like layers
+    ///
+    /// Only the `dot` engine ranks the tasks.
+    #[clap(long, action, conflicts_with_all = &["cluster", "interactive-clusters"])]
+    rank_by_namespace: bool,
+
+    /// Render one SVG per top-level namespace, next to the output file and
+    /// named after it, with the tasks of other namespaces linking to theirs
+    ///
+    /// When the output is a directory, such as `-o out/`, the pages are
+    /// `out/NAMESPACE.svg` instead, and `out/index.svg` for the tasks outside
+    /// of any namespace. The files written are listed on stdout.
+    #[clap(long, action)]
+    split_by_namespace: bool,
+
+    /// With `--split-by-namespace`, stack the pages in the output file, one
+    /// below the other under the name of their namespace, instead of writing
+    /// a file for each
+    #[clap(long, action, requires = "split-by-namespace")]
+    #[clap(conflicts_with = "interactive-clusters")]
+    merge: bool,
+
+    /// Embed the Taskfiles, with their path and SHA-256, in the metadata of
+    /// the SVG, so that it can be traced back to them
+    #[clap(long, action)]
+    embed_source: bool,
+
+    /// Leave the descriptions and summaries of the tasks out of every
+    /// output, for images shared outside the project
+    #[clap(long, action, conflicts_with = "embed-source")]
+    redact: bool,
+
+    /// Label the SVG and its tasks and dependencies for screen readers
+    #[clap(long, action)]
+    a11y: bool,
+
+    /// Note the `output` and `method` settings of the Taskfile under the
+    /// graph, when it sets them
+    #[clap(long, action)]
+    show_config: bool,
+
+    /// Write the name of the Taskfile, or else TEXT, above the graph, with
+    /// its numbers of tasks and cycles, so that the image tells what it shows
+    #[clap(long, value_name = "TEXT")]
+    title: Option<Option<String>>,
+
+    /// Run `task --version`, and warn when the installed Task doesn't
+    /// support the `version` the Taskfile declares
+    #[clap(long, action)]
+    version_check: bool,
+
+    /// Show namespaces separated by this character instead of `:`
+    ///
+    /// Only the labels change: dependencies still refer to tasks with `:`,
+    /// as they do for Task.
+    #[clap(long, value_name = "CHAR")]
+    namespace_separator: Option<char>,
+
+    /// Font for the text of the graph, passed as is to Graphviz
+    #[clap(long, value_name = "NAME")]
+    font: Option<String>,
+
+    /// Give each cycle its own color, instead of red for all of them
+    #[clap(long, action)]
+    distinct_cycles: bool,
+
+    /// Draw each cycle in a box around its tasks, labeled `cycle N`
+    ///
+    /// With `--cluster`, the box of a cycle is drawn in the cluster of the
+    /// innermost namespace all its tasks are in, and its tasks are taken out
+    /// of the clusters nested in that one.
+    #[clap(long, action, conflicts_with = "rank-by-namespace")]
+    cluster_cycles: bool,
+
+    /// Colors for `--distinct-cycles` and the clusters of `--legend`, in
+    /// place of their own, taken in turn
+    #[clap(long, value_name = "C1,C2,...")]
+    palette: Option<Palette>,
+
+    /// Draw tasks with `run: once` with a double border, as Task runs them
+    /// only once however many tasks depend on them
+    #[clap(long, action)]
+    annotate_run: bool,
+
+    /// Draw dependencies between tasks of different top-level namespaces
+    /// in bold, as they couple the included Taskfiles together
+    #[clap(long, action)]
+    highlight_cross_namespace: bool,
+
+    /// Draw the dependencies Task runs with `silent: true` in a lighter
+    /// color, as their commands aren't printed
+    #[clap(long, action)]
+    dim_silent_deps: bool,
+
+    /// Color theme
+    #[clap(long, value_enum, default_value_t)]
+    theme: Theme,
+
+    /// Leave the background of PNG output transparent, whatever the theme
+    #[clap(long, action)]
+    transparent: bool,
+
+    /// Graphviz layout engine
+    #[clap(long, value_enum, default_value = "dot")]
+    engine: Engine,
+
+    /// Scale the image down to fit in this size, in inches, as `WxH` or
+    /// `W,H`
+    ///
+    /// Graphviz only scales down; with a trailing `!`, such as `8x6!`, the
+    /// image is also scaled up, so that it fits the size exactly in one
+    /// dimension.
+    #[clap(long, value_name = "WxH")]
+    size: Option<Size>,
+
+    /// Give up rendering the image when Graphviz takes longer than this many
+    /// seconds
+    #[clap(long, value_name = "SECS")]
+    render_timeout: Option<u64>,
+
+    /// The Graphviz `dot` command to run, instead of the one found on `PATH`
+    /// [default: the `TASKDEP_DOT` environment variable]
+    #[clap(long, value_name = "PATH")]
+    dot_path: Option<PathBuf>,
+
+    /// Random seed for the initial layout, for reproducible renders
+    ///
+    /// Only the force-directed engines (`neato`, `fdp` and `sfdp`) start from
+    /// a random layout; the others ignore the seed.
+    #[clap(long, value_name = "N")]
+    seed: Option<u64>,
+}
+
+/// A warning, or the error taskdep stopped with, for `--warnings-json`
+struct Diagnostic {
+    /// `warning` or `error`
+    severity: &'static str,
+    /// What the warning is about, such as `undefined-dep`, for tools to tell
+    /// them apart without parsing the message
+    code: &'static str,
+    message: String,
+    /// The task the warning is about, if any, and where it's defined
+    task: Option<String>,
+    file: Option<String>,
+    line: Option<usize>,
+}
+
+/// Write diagnostics, and then `error` if any, as a JSON array of objects.
+fn write_diagnostics<W>(
+    diagnostics: &[Diagnostic],
+    error: Option<&anyhow::Error>,
+    mut out: W,
+) -> Result<()>
+where
+    W: Write,
+{
+    let error = error.map(|e| Diagnostic {
+        severity: "error",
+        code: "error",
+        message: format!("{e:#}"),
+        task: None,
+        file: None,
+        line: None,
+    });
+    let diagnostics = diagnostics
+        .iter()
+        .chain(&error)
+        .map(|d| {
+            serde_json::json!({
+                "severity": d.severity,
+                "code": d.code,
+                "message": d.message,
+                "file": d.file,
+                "line": d.line,
+                "task": d.task,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_writer_pretty(&mut out, &diagnostics)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// What [`run`] leaves to do, or to report, besides its result
+#[derive(Default)]
+struct Outcome {
+    /// The error reading the Taskfiles, under `--render-on-error`
+    incomplete: Option<anyhow::Error>,
+    /// The files the Taskfiles were read from, for `--watch`
+    files: Vec<PathBuf>,
+    /// The image written, for `--serve`
+    image: Option<PathBuf>,
+    /// The URL of the image, when it's to be opened in the browser
+    open: Option<String>,
+    /// Time spent in each phase, in the order they came in, for `--profile`
+    phases: Vec<(&'static str, Duration)>,
+    /// The warnings printed, for `--warnings-json`
+    diagnostics: Vec<Diagnostic>,
+    /// What the graph shows that sets the exit status, and that `--strict`
+    /// exits with an error for
+    findings: Vec<(Failure, String)>,
+}
+
+/// Why taskdep fails, from the most to the least important, each with its own
+/// exit status for scripts to tell them apart. Any other error exits with 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Failure {
+    /// A Taskfile can't be read or parsed
+    Parse,
+    /// Graphviz can't render the graph
+    Render,
+    /// The graph has cycles
+    Cycles,
+    /// Tasks depend on tasks defined nowhere, or, with `--strict`, on
+    /// namespaces
+    Undefined,
+}
+
+/// An error coming from a [`Failure`], shown as the error itself
+#[derive(Debug)]
+struct Failed {
+    failure: Failure,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for Failed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl Failure {
+    fn status(self) -> u8 {
+        match self {
+            Failure::Cycles => 2,
+            Failure::Undefined => 3,
+            Failure::Parse => 4,
+            Failure::Render => 5,
+        }
+    }
+
+    /// `error`, exiting with the status of this failure.
+    fn of(self, error: anyhow::Error) -> anyhow::Error {
+        Failed {
+            failure: self,
+            error,
+        }
+        .into()
+    }
+}
+
+/// The exit status for `result`: from the most important failure it comes
+/// from if it's an error, or else from the most important of `findings`.
+fn exit_status(result: &Result<()>, findings: &[Failure]) -> u8 {
+    match result {
+        Ok(()) => findings.iter().min().map_or(0, |f| f.status()),
+        Err(e) => failure(e).map_or(1, Failure::status),
+    }
+}
+
+/// What `graph` shows that sets the exit status: its cycles and the tasks
+/// depended on without being defined.
+fn findings(graph: &DiGraph<Node, Dep>) -> Vec<(Failure, String)> {
+    let stats = Stats::new(graph);
+    let mut findings = vec![];
+    if stats.cycles > 0 {
+        findings.push((
+            Failure::Cycles,
+            match stats.cycles {
+                1 => "the graph has a cycle".to_string(),
+                n => format!("the graph has {n} cycles"),
+            },
+        ));
+    }
+    if stats.undefined > 0 {
+        findings.push((
+            Failure::Undefined,
+            match stats.undefined {
+                1 => "a task is depended on without being defined".to_string(),
+                n => format!("{n} tasks are depended on without being defined"),
+            },
+        ));
+    }
+    findings
+}
+
+/// The most important failure `error` comes from, if any.
+fn failure(error: &anyhow::Error) -> Option<Failure> {
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<Failed>())
+        .map(|f| {
+            failure(&f.error).map_or(f.failure, |inner| inner.min(f.failure))
+        })
+        .min()
+}
+
+impl Outcome {
+    /// Print a warning about `task`, if any, on stderr, after where it's
+    /// defined, and keep it for `--warnings-json` with `code`.
+    fn warn(
+        &mut self,
+        code: &'static str,
+        task: Option<&Node>,
+        message: String,
+    ) {
+        eprintln!(
+            "warning: {}{message}",
+            task.map(location).unwrap_or_default()
+        );
+        self.diagnostics.push(Diagnostic {
+            severity: "warning",
+            code,
+            message,
+            task: task.map(|n| n.name.clone()),
+            file: task.and_then(|n| n.file.clone()),
+            line: task.and_then(|n| n.line),
+        });
+    }
+
+    /// Write the warnings, and then `error` if any, to `path`, the file of
+    /// `--warnings-json`.
+    fn write_diagnostics(
+        &self,
+        path: &str,
+        error: Option<&anyhow::Error>,
+    ) -> Result<()> {
+        let f = File::create(path).map_err(|e| anyhow!("{path}: {e}"))?;
+        write_diagnostics(&self.diagnostics, error, BufWriter::new(f))
+    }
+
+    /// Add the time since `start` to the time spent in `phase`.
+    fn phase(&mut self, phase: &'static str, start: Instant) {
+        let elapsed = start.elapsed();
+        match self.phases.iter_mut().find(|(p, _)| *p == phase) {
+            Some((_, total)) => *total += elapsed,
+            None => self.phases.push((phase, elapsed)),
+        }
+    }
+
+    fn print_phases(&self) {
+        for (phase, elapsed) in &self.phases {
+            eprintln!("{phase}: {:.1} ms", elapsed.as_secs_f64() * 1000.0);
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = e.print();
+            // Rather than 2, as clap does, which is for cycles
+            return ExitCode::from(u8::from(e.use_stderr()));
+        }
+    };
+    let mut outcome = Outcome::default();
+    let result = match args.watch {
+        true => watch(&args),
+        false => run_once(&args, &mut outcome),
+    };
+    if let Err(e) = &result {
+        // As Rust prints the error returned by `main`
+        eprintln!("Error: {e:?}");
+    }
+    let findings = outcome.findings.iter().map(|(f, _)| *f).collect::<Vec<_>>();
+    ExitCode::from(exit_status(&result, &findings))
+}
+
+/// Do as told by `args`, once, with the error to exit with, if any.
+fn run_once(args: &Args, outcome: &mut Outcome) -> Result<()> {
+    let mut result = run(args, outcome);
+    if result.is_ok() {
+        if let Some(e) = outcome.incomplete.take() {
+            result = Err(e.context("the graph is incomplete"));
+        } else if args.strict && !outcome.findings.is_empty() {
+            let failure = outcome.findings.iter().map(|(f, _)| *f).min();
+            let messages = outcome
+                .findings
+                .iter()
+                .map(|(_, message)| message.as_str())
+                .collect::<Vec<_>>();
+            let error = anyhow!("{}", messages.join("\n"));
+            result = Err(failure.unwrap().of(error));
+        }
+    }
+    if args.profile {
+        outcome.print_phases();
+    }
+    if let Some(path) = &args.warnings_json {
+        outcome.write_diagnostics(path, result.as_ref().err())?;
+    }
+    if let Some(url) = &outcome.open {
+        webbrowser::open(url)?;
+    }
+    result
+}
+
+/// Wait until one of `files` is written, created or removed.
+fn wait_for_change(files: &[PathBuf]) {
+    let modified =
+        |f: &PathBuf| fs::metadata(f).and_then(|m| m.modified()).ok();
+    let before = files.iter().map(modified).collect::<Vec<_>>();
+    while files.iter().map(modified).eq(before.iter().cloned()) {
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+/// Do as told by `args` again whenever one of the Taskfiles changes, until
+/// interrupted, serving the image with `--serve`.
+fn watch(args: &Args) -> Result<()> {
+    let mut listener = match args.serve {
+        true => Some(
+            TcpListener::bind(("127.0.0.1", args.port))
+                .map_err(|e| anyhow!("port {}: {e}", args.port))?,
+        ),
+        false => None,
+    };
+    let mut files = args.input.iter().map(PathBuf::from).collect::<Vec<_>>();
+    let mut first = true;
+    loop {
+        let mut outcome = Outcome::default();
+        let result = run(args, &mut outcome);
+        if args.profile {
+            outcome.print_phases();
+        }
+        if let Some(path) = &args.warnings_json {
+            let error = result.as_ref().err().or(outcome.incomplete.as_ref());
+            outcome.write_diagnostics(path, error)?;
+        }
+        match result {
+            Err(e) => eprintln!("error: {e:#}"),
+            Ok(()) => {
+                if let Some(e) = outcome.incomplete {
+                    eprintln!("error: the graph is incomplete: {e:#}");
+                }
+            }
+        }
+        if !outcome.files.is_empty() {
+            files = outcome.files;
+        }
+        // Serving only once there's an image
+        match (listener.take(), outcome.image) {
+            (Some(listener), Some(image)) => {
+                let port = listener.local_addr()?.port();
+                let url = format!("http://localhost:{port}");
+                serve(listener, image);
+                println!("serving on {url}");
+                if outcome.open.is_some() {
+                    webbrowser::open(&url)?;
+                }
+            }
+            (Some(unused), None) => listener = Some(unused),
+            (None, _) if first && !args.serve => {
+                if let Some(url) = &outcome.open {
+                    webbrowser::open(url)?;
+                }
+            }
+            (None, _) => (),
+        }
+        if first {
+            eprintln!("watching {} files for changes", files.len());
+            first = false;
+        }
+        wait_for_change(&files);
+    }
+}
+
+/// Do as told by `args`, leaving to [`main`] what's in `outcome`. With
+/// `--render-on-error`, the error reading the Taskfiles goes there, once the
+/// graph read so far is rendered.
+fn run(args: &Args, outcome: &mut Outcome) -> Result<()> {
+    let config = read_config().map_err(|e| Failure::Parse.of(e))?;
+    let format = resolve_format(
+        args.format,
+        env::var("TASKDEP_FORMAT").ok().as_deref(),
+        &config,
+    )?;
+    if args.serve && format != Format::Svg {
+        bail!("--serve only serves SVG images");
+    }
+    if args.serve && args.split_by_namespace && !args.merge {
+        bail!("--serve needs a single image; add --merge");
+    }
+    let mut discovered = None;
+    if let Some(dir) = &args.dir {
+        let mut found = find_taskfiles(Path::new(dir))?;
+        if args.gitignore {
+            let paths =
+                found.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>();
+            match ignored_by_git(Path::new(dir), &paths) {
+                Ok(ignored) => found.retain(|(p, _)| !ignored.contains(p)),
+                Err(e) => outcome.warn(
+                    "gitignore-failed",
+                    None,
+                    format!("--gitignore: {e:#}; reading every Taskfile"),
+                ),
+            }
+        }
+        if found.is_empty() {
+            bail!("no Taskfile found in {dir}");
+        }
+        eprintln!("found {} Taskfiles in {dir}", found.len());
+        discovered = Some(
+            found
+                .into_iter()
+                .map(|(p, ns)| (p.to_string_lossy().into_owned(), ns))
+                .collect::<Vec<_>>(),
+        );
+    }
+    if args.input.len() > 1 && args.input.iter().any(|i| i.contains('!')) {
+        bail!("a Taskfile inside an archive must be the only --input");
+    }
+    let (archive, taskfile) = match (&discovered, args.input[0].split_once('!'))
+    {
+        // The first Taskfile found, or given, names the output, and is the
+        // root one for `--show-config`
+        (Some(found), _) => (None, found[0].0.as_str()),
+        (None, Some((archive, taskfile))) => (Some(archive), taskfile),
+        (None, None) => (None, args.input[0].as_str()),
+    };
+    if args.stdin_format != StdinFormat::Taskfile
+        && (discovered.is_some() || !args.input.iter().any(|i| i == "-"))
+    {
+        outcome.warn(
+            "not-applicable",
+            None,
+            "--stdin-format only applies with --input -".into(),
+        );
+    }
+    let roots = match &discovered {
+        Some(found) => found.clone(),
+        None if archive.is_some() => vec![(taskfile.to_string(), vec![])],
+        None => input_roots(&args.input, args.namespace_by_filename),
+    };
+    if args.print_includes {
+        if archive.is_some() {
+            bail!("--print-includes can't read from an archive");
+        }
+        let root =
+            canonicalize(taskfile).map_err(|e| anyhow!("{taskfile}: {e}"))?;
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", root.to_string_lossy())?;
+        let mut ancestors = vec![root];
+        print_includes(taskfile, &HashMap::new(), &mut ancestors, &mut stdout)?;
+        return Ok(());
+    }
+    let mut source: Box<dyn Source> = match archive {
+        Some(archive) => {
+            let zip = File::open(archive)
+                .map_err(|e| anyhow!("{archive}: {e}"))
+                .and_then(|f| {
+                    ZipSource::new(f).with_context(|| archive.to_string())
+                });
+            Box::new(zip.map_err(|e| Failure::Parse.of(e))?)
+        }
+        None => Box::new(FileSystem::default()),
+    };
+    if args.input_format != InputFormat::Auto {
+        for (path, _) in roots.iter().filter(|_| discovered.is_none()) {
+            source = Box::new(WithFormat::new(source, path, args.input_format));
+        }
+    }
+    if args.schema {
+        source = Box::new(Validating::new(source));
+    }
+    let mut nodes = HashMap::new();
+    let mut graph: DiGraph<Node, _> = DiGraph::new();
+    let mut namespaces = HashMap::new();
+    let mut failed = None;
+    let started = Instant::now();
+    for (path, namespace) in &roots {
+        // Directories `a:b` and `a/b` both give namespace `a:b`. Several
+        // `--input`s without namespaces are combined instead
+        if let Some(other) = namespaces
+            .get(&namespace.join(":"))
+            .filter(|_| !namespace.is_empty())
+        {
+            outcome.warn(
+                "duplicate-namespace",
+                None,
+                format!("{path}: skipped, as {other} has the same namespace"),
+            );
+            continue;
+        }
+        namespaces.insert(namespace.join(":"), path);
+        let built = match (path.as_str(), args.stdin_format) {
+            ("-", StdinFormat::Json) => source
+                .open(path)
+                .and_then(|f| read_json(f, &mut nodes, &mut graph))
+                .context("the standard input"),
+            _ => build_graph_from(
+                source.as_mut(),
+                path,
+                namespace,
+                &mut nodes,
+                &mut graph,
+                !args.no_includes,
+                &HashMap::new(),
+            ),
+        };
+        if let Err(e) = built {
+            failed = Some(Failure::Parse.of(e));
+            break;
+        }
+    }
+    if roots.len() > 1 {
+        dedup_deps(&mut graph);
+    }
+    outcome.phase("parsing the Taskfiles, building the graph", started);
+    let started = Instant::now();
+    outcome.files = source.files();
+    outcome.files.extend(archive.map(PathBuf::from));
+    outcome.files.extend(args.overlay.iter().map(PathBuf::from));
+    if let Some(e) = failed {
+        if !args.render_on_error {
+            return Err(e);
+        }
+        outcome.incomplete = Some(e);
+    }
+    if let Some(overlay) = &args.overlay {
+        File::open(overlay)
+            .map_err(|e| anyhow!("{overlay}: {e}"))
+            .and_then(|f| apply_overlay(f, &mut nodes, &mut graph))
+            .map_err(|e| Failure::Parse.of(e))?;
+    }
+    if args.redact {
+        redact(&mut graph);
+    }
+    for idx in colon_names(&graph) {
+        let n = &graph[idx];
+        let (namespace, task) =
+            n.local_name().split_once(':').unwrap_or_default();
+        let message = format!(
+            "task `{}` has `:` in its name, which Task can't tell apart from \
+             task `{task}` of namespace `{namespace}`",
+            n.name
+        );
+        outcome.warn("colon-in-name", Some(n), message);
+    }
+    for n in graph
+        .node_weights()
+        .filter(|n| !n.also_defined_in.is_empty())
+    {
+        let message = format!(
+            "task `{}` is also defined in {}, flattened into the same \
+             namespace; Task refuses that, and the dependencies of all of \
+             them are drawn",
+            n.name,
+            n.also_defined_in.join(", ")
+        );
+        outcome.warn("flatten-collision", Some(n), message);
+    }
+    let mut mistakes = vec![];
+    for (idx, tasks) in namespace_deps(&graph) {
+        for task in graph.neighbors_directed(idx, Direction::Outgoing) {
+            let message = format!(
+                "task `{}` depends on `{}`, which is a namespace rather than \
+                 a task; did you mean one of {}?",
+                graph[task].name,
+                graph[idx].name,
+                tasks.join(", ")
+            );
+            mistakes.push((task, message));
+        }
+    }
+    if args.strict && !mistakes.is_empty() {
+        let mistakes = mistakes
+            .iter()
+            .map(|(task, message)| {
+                format!("{}{message}", location(&graph[*task]))
+            })
+            .collect::<Vec<_>>();
+        return Err(Failure::Undefined.of(anyhow!("{}", mistakes.join("\n"))));
+    }
+    for (task, message) in mistakes {
+        outcome.warn("namespace-dep", Some(&graph[task]), message);
+    }
+    // Only once the graph is written, unless --strict makes them errors
+    let findings = findings(&graph);
+    if args.strict {
+        outcome.findings = findings.clone();
+    }
+    let mut entrypoints = match &args.entrypoints {
+        Some(path) => {
+            let entrypoints = File::open(path)
+                .map_err(|e| anyhow!("{path}: {e}"))
+                .and_then(|f| {
+                    parse_task_list(f, &nodes, &graph).context(path.clone())
+                });
+            Some(entrypoints.map_err(|e| Failure::Parse.of(e))?)
+        }
+        None => None,
+    };
+    if args.list_cycles {
+        let choice = if args.no_color || !std::io::stdout().is_terminal() {
+            ColorChoice::Never
+        } else {
+            // Still turned off by `NO_COLOR`, or `TERM=dumb`
+            ColorChoice::Auto
+        };
+        return print_cycles(&graph, &mut StandardStream::stdout(choice));
+    }
+    if args.list_tasks {
+        let mut names = graph
+            .node_weights()
+            .filter(|n| n.defined || !args.only_defined)
+            .map(|n| n.name.as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            println!("{name}");
+        }
+        return Ok(());
+    }
+    if let Some(min) = args.long_chains {
+        let mut chains = linear_chains(&graph);
+        chains.retain(|chain| chain.len() >= min);
+        let names = |chain: &Vec<NodeIndex>| {
+            chain
+                .iter()
+                .map(|&idx| graph[idx].name.as_str())
+                .collect::<Vec<_>>()
+        };
+        chains.sort_by_key(|chain| {
+            (std::cmp::Reverse(chain.len()), names(chain))
+        });
+        for chain in &chains {
+            println!("{} ({} tasks)", names(chain).join(" -> "), chain.len());
+        }
+        return Ok(());
+    }
+    if let Some(name) = &args.explain {
+        find_task(&nodes, &graph, name)?;
+        let tasks = TaskGraph::new(graph);
+        return explain(&tasks, name, &mut std::io::stdout().lock());
+    }
+    if let Some(name) = &args.plan {
+        let idx = find_task(&nodes, &graph, name)?;
+        let order = plan(&graph, idx).map_err(|looping| {
+            let names = looping
+                .iter()
+                .map(|&idx| graph[idx].name.as_str())
+                .collect::<Vec<_>>();
+            anyhow!("can't plan `{name}`: cycle between {}", names.join(", "))
+        })?;
+        for idx in order {
+            println!("{}", graph[idx].name);
+        }
+        return Ok(());
+    }
+    if args.topo {
+        for group in topo_groups(&graph) {
+            let names = group
+                .iter()
+                .map(|&idx| graph[idx].name.as_str())
+                .collect::<Vec<_>>();
+            match names.as_slice() {
+                [name] if !graph.contains_edge(group[0], group[0]) => {
+                    println!("{name}")
+                }
+                _ => println!("[cycle: {}]", names.join(", ")),
+            }
+        }
+        return Ok(());
+    }
+    if args.blocked_by_cycles {
+        for (cycle, blocked) in blocked_by_cycles(&graph) {
+            let names = |tasks: &[NodeIndex]| {
+                tasks
+                    .iter()
+                    .map(|&idx| graph[idx].name.as_str())
+                    .collect::<Vec<_>>()
+            };
+            println!("[cycle: {}]", names(&cycle).join(", "));
+            for name in names(&blocked) {
+                println!("  {name}");
+            }
+        }
+        return Ok(());
+    }
+    match args.badge {
+        Some(Badge::Text) => {
+            println!("{}", Stats::new(&graph));
+            return Ok(());
+        }
+        Some(Badge::Svg) => {
+            let stats = Stats::new(&graph);
+            let color = if stats.cycles > 0 { "#e05d44" } else { "#4c1" };
+            let output = args.output.clone().unwrap_or_else(|| {
+                let stem = Path::new(taskfile)
+                    .file_stem()
+                    .filter(|_| taskfile != "-")
+                    .map(|s| s.to_string_lossy())
+                    .unwrap_or_else(|| "Taskfile".into());
+                format!("{stem}.badge.svg")
+            });
+            if is_any_of(Path::new(&output), &source.files()) {
+                bail!("refusing to overwrite input file {output}");
+            }
+            let svg = badge_svg("taskdep", &stats.to_string(), color);
+            return std::fs::write(&output, svg)
+                .map_err(|e| anyhow!("{output}: {e}"));
+        }
+        None => {}
+    }
+    if args.suggest_fixes {
+        for (cycle, edges) in feedback_edges(&graph) {
+            let names = cycle
+                .iter()
+                .map(|&idx| graph[idx].name.as_str())
+                .collect::<Vec<_>>();
+            println!("cycle: {}", names.join(", "));
+            for (from, to) in
+                edges.iter().flat_map(|&e| graph.edge_endpoints(e))
+            {
+                println!(
+                    "  remove dependency {} -> {}",
+                    graph[from].name, graph[to].name
+                );
+            }
+        }
+        return Ok(());
+    }
+    if args.find_redundant_deps {
+        let mut found = redundant_deps(&graph)
+            .into_iter()
+            .map(|(e, path)| {
+                let (from, to) = graph.edge_endpoints(e).unwrap();
+                let names = |path: &[NodeIndex]| {
+                    path.iter()
+                        .map(|&idx| graph[idx].name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                };
+                (names(&[from, to]), names(&path))
+            })
+            .collect::<Vec<_>>();
+        found.sort();
+        for (edge, path) in found {
+            println!("remove dependency {edge}, implied by {path}");
+        }
+        return Ok(());
+    }
+    if let Some(Between { from, to }) = &args.all_paths {
+        let (start, end) = (task_in(&graph, from)?, task_in(&graph, to)?);
+        let (paths, complete) =
+            dependency_paths(&graph, start, end, args.max_paths);
+        if paths.is_empty() {
+            bail!("`{from}` doesn't depend on `{to}`, directly or not");
+        }
+        for path in &paths {
+            let names = path
+                .iter()
+                .rev()
+                .map(|&idx| graph[idx].name.as_str())
+                .collect::<Vec<_>>();
+            println!("{}", names.join(" -> "));
+        }
+        if !complete {
+            outcome.warn(
+                "truncated",
+                None,
+                format!(
+                    "stopped after {} paths; raise --max-paths to see more",
+                    paths.len()
+                ),
+            );
+        }
+        return Ok(());
+    }
+    if args.unreachable {
+        let seen =
+            reachable(&graph, &top_level(&graph, entrypoints.as_deref()));
+        let mut dead = graph
+            .node_indices()
+            .filter(|idx| graph[*idx].defined && !seen.contains(idx))
+            .map(|idx| graph[idx].name.as_str())
+            .collect::<Vec<_>>();
+        dead.sort();
+        for name in dead {
+            println!("{name}");
+        }
+        return Ok(());
+    }
+    if let Some(path) = &args.compare_order {
+        let order = File::open(path)
+            .map_err(|e| anyhow!("{path}: {e}"))
+            .and_then(|f| {
+                parse_task_list(f, &nodes, &graph).context(path.clone())
+            })
+            .map_err(|e| Failure::Parse.of(e))?;
+        return check_order(&graph, &order).context(path.clone());
+    }
+    if let Some(max) = args.assert_max_depth {
+        return check_max_depth(&graph, max);
+    }
+    if let Some(name) = &args.focus {
+        let task = find_task(&nodes, &graph, name)?;
+        let (sub, moved) =
+            subgraph(&graph, &focus(&graph, task, args.direct_only));
+        graph = sub;
+        remap(&mut entrypoints, &moved);
+    }
+    if let Some(names) = &args.reachable_from {
+        let roots = match names.is_empty() {
+            true => top_level(&graph, entrypoints.as_deref()),
+            false => names
+                .iter()
+                .map(|name| task_in(&graph, name))
+                .collect::<Result<_>>()?,
+        };
+        let (sub, moved) = subgraph(&graph, &reachable(&graph, &roots));
+        graph = sub;
+        remap(&mut entrypoints, &moved);
+    }
+    if let Some(pattern) = &args.prune_regex {
+        let (sub, moved) = subgraph(&graph, &unmatched(&graph, pattern));
+        graph = sub;
+        remap(&mut entrypoints, &moved);
+    }
+    if !args.exclude_namespace.is_empty() {
+        let namespaces = args
+            .exclude_namespace
+            .iter()
+            .map(|ns| ns.trim_end_matches(':').to_string())
+            .collect::<Vec<_>>();
+        for namespace in &namespaces {
+            if !graph
+                .node_weights()
+                .any(|n| in_namespace(&n.name, namespace))
+            {
+                outcome.warn(
+                    "empty-namespace",
+                    None,
+                    format!("--exclude-namespace: no tasks in `{namespace}`"),
+                );
+            }
+        }
+        let (sub, moved) =
+            subgraph(&graph, &outside_namespaces(&graph, &namespaces));
+        graph = sub;
+        remap(&mut entrypoints, &moved);
+    }
+    if args.no_phantom {
+        let mut keep = HashSet::new();
+        for idx in graph.node_indices() {
+            if graph[idx].defined {
+                keep.insert(idx);
+                continue;
+            }
+            for task in graph.neighbors_directed(idx, Direction::Outgoing) {
+                let message = format!(
+                    "task `{}` depends on `{}`, which isn't defined in any \
+                     Taskfile; leaving it out",
+                    graph[task].name, graph[idx].name
+                );
+                outcome.warn("undefined-dep", Some(&graph[task]), message);
+            }
+        }
+        let (sub, moved) = subgraph(&graph, &keep);
+        graph = sub;
+        remap(&mut entrypoints, &moved);
+    }
+    let mut changed = vec![];
+    if let Some(since) = &args.since {
+        if archive.is_some() {
+            bail!("--since can't read from an archive");
+        }
+        match changed_files(since).context("--since")? {
+            Some(files) => {
+                let touched = graph
+                    .node_indices()
+                    .filter(|&idx| {
+                        let file = graph[idx].file.as_ref();
+                        file.and_then(|f| canonicalize(f).ok())
+                            .is_some_and(|f| files.contains(&f))
+                    })
+                    .collect::<Vec<_>>();
+                let mut keep = touched.iter().copied().collect::<HashSet<_>>();
+                keep.extend(
+                    touched
+                        .iter()
+                        .flat_map(|&idx| graph.neighbors_undirected(idx)),
+                );
+                changed = touched
+                    .iter()
+                    .map(|&idx| graph[idx].name.clone())
+                    .collect();
+                let (sub, moved) = subgraph(&graph, &keep);
+                graph = sub;
+                remap(&mut entrypoints, &moved);
+            }
+            None => outcome.warn(
+                "since-failed",
+                None,
+                "--since: not a git repository; showing every task".to_string(),
+            ),
+        }
+    }
+    // The tasks of each cycle merged by --condense, by the name of the task
+    // they were merged into
+    let mut cycle_pages = vec![];
+    if args.condense {
+        let (condensed, moved) = condense(&graph);
+        if args.drill_down {
+            let mut members = HashMap::<_, HashSet<_>>::new();
+            for (&idx, &merged) in &moved {
+                members.entry(merged).or_default().insert(idx);
+            }
+            let mut merged = members
+                .into_iter()
+                .filter(|(_, members)| members.len() > 1)
+                .collect::<Vec<_>>();
+            merged.sort_by_key(|&(idx, _)| idx);
+            for (idx, members) in merged {
+                let (page, _) = subgraph(&graph, &members);
+                cycle_pages.push((condensed[idx].name.clone(), page));
+            }
+        }
+        graph = condensed;
+        remap(&mut entrypoints, &moved);
+    }
+    if args.collapse_leaves {
+        let (collapsed, moved) = collapse_leaves(&graph);
+        graph = collapsed;
+        remap(&mut entrypoints, &moved);
+    }
+    // With --split-by-namespace, pages can go to a directory of their own
+    let own_dir = args.split_by_namespace
+        && !args.merge
+        && args
+            .output
+            .as_ref()
+            .is_some_and(|o| o.ends_with('/') || Path::new(o).is_dir());
+    let output = match &args.output {
+        Some(dir) if own_dir => {
+            std::fs::create_dir_all(dir).map_err(|e| anyhow!("{dir}: {e}"))?;
+            Path::new(dir)
+                .join(format!("index.{}", format.extension()))
+                .to_string_lossy()
+                .into_owned()
+        }
+        Some(output) => output.clone(),
+        None => {
+            let stem = Path::new(taskfile)
+                .file_stem()
+                .filter(|_| taskfile != "-")
+                .map(|s| s.to_string_lossy())
+                .unwrap_or_else(|| "Taskfile".into());
+            format!("{stem}.{}", format.extension())
+        }
+    };
+    if args.split_by_namespace && format != Format::Svg {
+        bail!("--split-by-namespace only applies to SVG output");
+    }
+    if args.drill_down && format != Format::Svg {
+        bail!("--drill-down only applies to SVG output");
+    }
+    let cycle_file =
+        |i: usize| page_file(&output, &format!("cycle{}", i + 1), false);
+    let mut pages = vec![];
+    if args.split_by_namespace {
+        let mut namespaces = namespace_pages(&graph);
+        // The output file is always written, so that there is a page to open
+        namespaces.entry("").or_default();
+        for (namespace, keep) in namespaces {
+            let (page, _) = subgraph(&graph, &keep);
+            pages.push((namespace.to_string(), page));
+        }
+    }
+    let mut inputs = source.files();
+    inputs.extend(archive.and_then(|a| canonicalize(a).ok()));
+    let extra = [&args.overlay, &args.entrypoints, &args.colors];
+    for path in extra.into_iter().flatten() {
+        inputs.extend(canonicalize(path));
+    }
+    // The page for the tasks outside of any namespace is the output file
+    let mut files = vec![(PathBuf::from(&output), "the output".to_string())];
+    files.extend(
+        pages
+            .iter()
+            .filter(|(namespace, _)| !args.merge && !namespace.is_empty())
+            .map(|(namespace, _)| {
+                (
+                    page_file(&output, namespace, own_dir),
+                    format!("the page of namespace `{namespace}`"),
+                )
+            }),
+    );
+    files.extend(
+        (0..cycle_pages.len())
+            .map(|i| (cycle_file(i), format!("the page of cycle {}", i + 1))),
+    );
+    files.extend(
+        args.also_dot
+            .iter()
+            .map(|p| (PathBuf::from(p), "the DOT source".to_string())),
+    );
+    for (file, _) in &files {
+        if is_any_of(file, &inputs) {
+            bail!("refusing to overwrite input file {}", file.display());
+        }
+    }
+    check_distinct(&files)?;
+    if args.also_dot.is_some() && (format == Format::Json || !pages.is_empty())
+    {
+        let message = "--also-dot only applies to a graph rendered as one \
+                       image, without --format json or --split-by-namespace";
+        outcome.warn("not-applicable", None, message.into());
+    }
+    if format == Format::Json {
+        let f = File::create(&output).map_err(|e| anyhow!("{output}: {e}"))?;
+        outcome.findings = findings;
+        return write_json(&graph, BufWriter::new(f));
+    }
+    let mut options = DotOptions {
+        label: args.label,
+        wrap: args.wrap_labels,
+        theme: args.theme,
+        clusters: args.cluster,
+        separator: args.namespace_separator,
+        distinct_cycles: args.distinct_cycles,
+        cycle_clusters: args.cluster_cycles,
+        palette: args.palette.clone().map(|p| p.0).unwrap_or_default(),
+        interactive_clusters: args.interactive_clusters,
+        namespace_legend: args.legend,
+        degrees: args.degree_labels,
+        mono: args.mono,
+        rank_by_namespace: args.rank_by_namespace,
+        reverse_edges: args.reverse_edges,
+        name_ids: args.name_ids && format == Format::Dot,
+        ..Default::default()
+    };
+    if args.name_ids && format != Format::Dot {
+        let message = "--name-ids only applies to --format dot";
+        outcome.warn("not-applicable", None, message.into());
+    }
+    if args.palette.is_some() && !args.distinct_cycles && !args.legend {
+        outcome.warn(
+            "not-applicable",
+            None,
+            "--palette only applies with --distinct-cycles or --legend".into(),
+        );
+    }
+    if args.legend && !args.cluster && !args.interactive_clusters {
+        outcome.warn(
+            "not-applicable",
+            None,
+            "--legend only applies with --cluster".into(),
+        );
+    }
+    if let Some(font) = &args.font {
+        let attr = format!("fontname=\"{}\"", escape(font));
+        options.graph_attrs.push(attr.clone());
+        options.node_attrs.push(attr.clone());
+        options.edge_attrs.push(attr);
+    }
+    if args.show_config {
+        let root =
+            parse(source.open(taskfile)?).context(taskfile.to_string())?;
+        let settings = [("output", root.output), ("method", root.method)]
+            .into_iter()
+            .filter_map(|(key, value)| Some(format!("{key}: {}", value?)))
+            .collect::<Vec<_>>();
+        if !settings.is_empty() {
+            options.footer = Some(settings.join(", "));
+        }
+    }
+    if args.version_check {
+        check_task_version(source.as_mut(), taskfile, outcome)?;
+    }
+    if let Some(size) = args.size {
+        // Like the seed, kept by DOT output
+        options.graph_attrs.push(format!("size=\"{size}\""));
+    }
+    if let Some(seed) = args.seed {
+        // A graph attribute rather than `-Gstart`, so that DOT output keeps
+        // it for later renders
+        options.graph_attrs.push(format!("start=\"{seed}\""));
+    }
+    if args.transparent {
+        if format == Format::Png {
+            // Comes after the background of the theme, so it wins
+            options
+                .graph_attrs
+                .push("bgcolor=\"transparent\"".to_string());
+        } else {
+            outcome.warn(
+                "not-applicable",
+                None,
+                "--transparent only applies to PNG output".into(),
+            );
+        }
+    }
+    if args.interactive_clusters && format != Format::Svg {
+        outcome.warn(
+            "not-applicable",
+            None,
+            "--interactive-clusters only applies to SVG output".into(),
+        );
+    }
+    let mut styles = Styles::default();
+    // The tasks of the cycles drawn on their own with --drill-down too
+    let tasks = || {
+        graph
+            .node_weights()
+            .chain(cycle_pages.iter().flat_map(|(_, p)| p.node_weights()))
+    };
+    if let Some(template) = &args.link_template {
+        for n in tasks() {
+            styles.node(
+                &n.name,
+                format!("URL=\"{}\" target=\"_top\"", task_link(template, n)),
+            );
+        }
+    }
+    if args.annotate_run {
+        for n in tasks() {
+            if n.run.as_deref() == Some("once") {
+                // In place of the tooltip of the description, rather than
+                // hiding it
+                let tooltip = match n.summary.as_ref().or(n.desc.as_ref()) {
+                    Some(desc) => format!("{desc} (run: once)"),
+                    None => "run: once".to_string(),
+                };
+                styles.node(
+                    &n.name,
+                    format!(
+                        "peripheries=\"2\" tooltip=\"{}\"",
+                        escape(&tooltip)
+                    ),
+                );
+            }
+        }
+    }
+    for name in &changed {
+        styles.node(name, "penwidth=\"3\"".to_string());
+    }
+    if let Some(path) = &args.colors {
+        let colors = File::open(path)
+            .map_err(|e| anyhow!("{path}: {e}"))
+            .and_then(|f| ColorMap::parse(f).context(path.clone()))
+            .map_err(|e| Failure::Parse.of(e))?;
+        let mut in_cycle = cycles(&graph)
+            .into_iter()
+            .flatten()
+            .map(|idx| graph[idx].name.as_str())
+            .collect::<HashSet<_>>();
+        in_cycle.extend(
+            cycle_pages
+                .iter()
+                .flat_map(|(_, p)| p.node_weights().map(|n| n.name.as_str())),
+        );
+        for n in tasks() {
+            if in_cycle.contains(n.name.as_str())
+                && !args.colors_override_cycles
+            {
+                continue;
+            }
+            let Some(given) = colors.colors(&n.name) else {
+                continue;
+            };
+            if let Some(color) = &given.color {
+                styles.node(&n.name, format!("color=\"{}\"", escape(color)));
+            }
+            if let Some(fill) = &given.fill {
+                let style = if n.defined { "filled" } else { "filled,dashed" };
+                styles.node(
+                    &n.name,
+                    format!("style=\"{style}\" fillcolor=\"{}\"", escape(fill)),
+                );
+            }
+        }
+    }
+    let cycle_styles = styles.clone();
+    for (i, (name, _)) in cycle_pages.iter().enumerate() {
+        let file = cycle_file(i);
+        let link = file.file_name().unwrap_or_default().to_string_lossy();
+        styles.node(
+            name,
+            format!("URL=\"{}\" target=\"_top\"", link.replace('"', "%22")),
+        );
+    }
+    if args.highlight_cross_namespace {
+        const CROSS_NAMESPACE: &str = "style=\"bold\" penwidth=\"2\"";
+        for e in graph.edge_indices() {
+            let (from, to) = graph.edge_endpoints(e).unwrap();
+            let (from, to) = (&graph[from].name, &graph[to].name);
+            if top_namespace(from) != top_namespace(to) {
+                styles.edge(from, to, CROSS_NAMESPACE);
+            }
+        }
+        options
+            .legend
+            .push(("cross-namespace".to_string(), CROSS_NAMESPACE.to_string()));
+    }
+    if args.dim_silent_deps {
+        const SILENT: &str = "color=\"#aaaaaa\"";
+        for e in graph.edge_indices().filter(|&e| graph[e].silent) {
+            let (from, to) = graph.edge_endpoints(e).unwrap();
+            styles.edge(&graph[from].name, &graph[to].name, SILENT);
+        }
+        options
+            .legend
+            .push(("silent".to_string(), SILENT.to_string()));
+    }
+    if args.heatmap {
+        let depths = match &entrypoints {
+            Some(entrypoints) => depths_from(&graph, entrypoints),
+            None => depths(&graph),
+        };
+        let max = depths.values().copied().max().unwrap_or_default();
+        for (idx, depth) in depths {
+            let n = &graph[idx];
+            let style = if n.defined { "filled" } else { "filled,dashed" };
+            styles.node(
+                &n.name,
+                format!(
+                    "style=\"{style}\" fillcolor=\"{}\" fontcolor=\"black\"",
+                    heat_color(depth, max)
+                ),
+            );
+        }
+    }
+    if let Some(Between { from, to }) = &args.path {
+        const ON_PATH: &str = "color=\"#1f77b4\" penwidth=\"2.5\"";
+        const DIMMED: &str = "color=\"#cccccc\" fontcolor=\"#999999\"";
+        let (start, end) = (task_in(&graph, from)?, task_in(&graph, to)?);
+        let Some(path) = dependency_path(&graph, start, end) else {
+            let reverse = match dependency_path(&graph, end, start) {
+                Some(_) => format!(", though `{to}` depends on `{from}`"),
+                None => String::new(),
+            };
+            bail!(
+                "`{from}` doesn't depend on `{to}`, directly or not{reverse}"
+            );
+        };
+        let names = path.iter().map(|&idx| &graph[idx].name);
+        let on_path = names.clone().collect::<HashSet<_>>();
+        // A dependency between two tasks of the path can still be off it
+        let steps = names
+            .clone()
+            .zip(names.skip(1))
+            .map(|(task, dep)| (dep, task))
+            .collect::<HashSet<_>>();
+        for &(dep, task) in &steps {
+            styles.edge(dep, task, ON_PATH);
+        }
+        for n in graph.node_weights() {
+            if on_path.contains(&n.name) {
+                styles.node(&n.name, ON_PATH);
+            } else if args.dim_others {
+                styles.node(&n.name, DIMMED);
+            }
+        }
+        if args.dim_others {
+            for e in graph.edge_indices() {
+                let (dep, task) = graph.edge_endpoints(e).unwrap();
+                let (dep, task) = (&graph[dep].name, &graph[task].name);
+                if !steps.contains(&(dep, task)) {
+                    styles.edge(dep, task, DIMMED);
+                }
+            }
+        }
+    }
+    if args.rank_by_namespace
+        && args.engine != Engine::Dot
+        && format != Format::Dot
+    {
+        outcome.warn(
+            "not-applicable",
+            None,
+            format!(
+                "--rank-by-namespace has no effect with the `{}` engine",
+                args.engine.name()
+            ),
+        );
+    }
+    if args.seed.is_some()
+        && !args.engine.is_force_directed()
+        && format != Format::Dot
+    {
+        outcome.warn(
+            "not-applicable",
+            None,
+            format!(
+                "--seed has no effect with the `{}` engine",
+                args.engine.name()
+            ),
+        );
+    }
+    let mut sources = vec![];
+    if args.embed_source {
+        if format == Format::Svg {
+            let mut files = graph
+                .node_weights()
+                .filter_map(|n| n.file.as_deref())
+                .collect::<Vec<_>>();
+            files.push(taskfile);
+            files.sort();
+            files.dedup();
+            for file in files {
+                let mut contents = vec![];
+                source.open(file)?.read_to_end(&mut contents)?;
+                sources.push((file.to_string(), contents));
+            }
+        } else {
+            outcome.warn(
+                "not-applicable",
+                None,
+                "--embed-source only applies to SVG output".into(),
+            );
+        }
+    }
+    if args.a11y && format != Format::Svg {
+        outcome.warn(
+            "not-applicable",
+            None,
+            "--a11y only applies to SVG output".into(),
+        );
+    }
+    let title = args.title.as_ref().map(|title| {
+        let name = || match taskfile {
+            "-" => "Taskfile".to_string(),
+            path => Path::new(path)
+                .file_name()
+                .map_or(path.into(), |name| name.to_string_lossy().into()),
+        };
+        title.clone().unwrap_or_else(name)
+    });
+    // Counting what each page draws
+    let titled = |g: &DiGraph<Node, Dep>| {
+        title
+            .as_ref()
+            .map(|title| format!("{title}\n{}", Stats::new(g)))
+    };
+    // Without `relabel`, the tasks of `g` are only counted
+    let accessible = |contents: Vec<u8>,
+                      g: &DiGraph<Node, Dep>,
+                      label,
+                      relabel: bool| {
+        if !args.a11y || format != Format::Svg {
+            return contents;
+        }
+        let count = |n, one, many| match n {
+            1 => format!("1 {one}"),
+            n => format!("{n} {many}"),
+        };
+        let description = format!(
+            "{} and {}",
+            count(g.node_count(), "task", "tasks"),
+            count(g.edge_count(), "dependency", "dependencies")
+        );
+        let tasks = match relabel {
+            true => g
+                .node_weights()
+                .map(|n| (n.name.clone(), n.desc.clone().or(n.summary.clone())))
+                .collect(),
+            false => vec![],
+        };
+        add_labels(
+            &contents,
+            &format!("Dependencies of the tasks of {label}"),
+            &description,
+            &tasks,
+        )
+    };
+    let embed = |contents: Vec<u8>| match sources.is_empty() {
+        true => contents,
+        false => embed_sources(&contents, &sources),
+    };
+    outcome.phase("rewriting the graph", started);
+    if pages.is_empty() {
+        let dot_copy = args.also_dot.as_deref();
+        options.title = titled(&graph);
+        let contents =
+            draw(&styles, &graph, &options, format, args, outcome, dot_copy)?;
+        let contents = accessible(contents, &graph, taskfile.to_string(), true);
+        File::create(&output)?.write_all(&embed(contents))?;
+    }
+    if !cycle_pages.is_empty() {
+        println!("{output}");
+    }
+    for (i, (_, page)) in cycle_pages.iter().enumerate() {
+        options.title = titled(page);
+        let contents =
+            draw(&cycle_styles, page, &options, format, args, outcome, None)?;
+        let label = format!("cycle {} of {taskfile}", i + 1);
+        let contents = accessible(contents, page, label, true);
+        let file = cycle_file(i);
+        File::create(&file)
+            .map_err(|e| anyhow!("{}: {e}", file.display()))?
+            .write_all(&embed(contents))?;
+        println!("{}", file.display());
+    }
+    let mut merged = vec![];
+    for (namespace, page) in &pages {
+        let mut styles = styles.clone();
+        for n in page.node_weights() {
+            let other = top_namespace(&n.name);
+            if other != namespace {
+                let file = page_file(&output, other, own_dir);
+                let link = match args.merge {
+                    true => format!("#{}", page_id(other)).into(),
+                    false => {
+                        file.file_name().unwrap_or_default().to_string_lossy()
+                    }
+                };
+                styles.node(
+                    &n.name,
+                    format!(
+                        "URL=\"{}\" target=\"_top\" tooltip=\"{}\"",
+                        link.replace('"', "%22"),
+                        escape(&link)
+                    ),
+                );
+            }
+        }
+        options.title = titled(page);
+        let contents =
+            draw(&styles, page, &options, format, args, outcome, None)?;
+        let label = match namespace.as_str() {
+            "" => taskfile.to_string(),
+            namespace => format!("namespace {namespace} of {taskfile}"),
+        };
+        let contents = accessible(contents, page, label, true);
+        if args.merge {
+            merged.push((namespace.clone(), contents));
+            continue;
+        }
+        let file = page_file(&output, namespace, own_dir);
+        File::create(&file)
+            .map_err(|e| anyhow!("{}: {e}", file.display()))?
+            .write_all(&embed(contents))?;
+        println!("{}", file.display());
+    }
+    if args.merge {
+        // The pages are labeled already, which leaves the root
+        let contents = merge_svgs(&merged);
+        let contents = accessible(contents, &graph, taskfile.into(), false);
+        File::create(&output)?.write_all(&embed(contents))?;
+        println!("{output}");
+    }
+    let interactive =
+        std::io::stdout().is_terminal() || std::io::stderr().is_terminal();
+    outcome.image = Some(output.clone().into());
+    outcome.findings = findings;
+    if (args.open || !args.silent && interactive) && format.is_image() {
+        let taskfile = canonicalize(&output)?;
+        outcome.open = Some(format!("file://{}", taskfile.to_string_lossy()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        check_distinct, check_max_depth, check_order, dedup_deps, exit_status,
+        explain, input_roots, is_any_of, page_file, parse_config,
+        parse_task_list, print_cycles, redact, remap, resolve_format,
+        task_link, top_level, write_diagnostics, Between, Diagnostic, Failure,
+        Format, Palette, Settings, Size,
+    };
+    use indoc::indoc;
+    use petgraph::graph::{DiGraph, NodeIndex};
+    use std::collections::HashMap;
+    use std::fs::{canonicalize, File};
+    use std::io::{Cursor, Write};
+    use std::path::{Path, PathBuf};
+    use taskdep::source::FileSystem;
+    use taskdep::{
+        build_graph, build_graph_from, dot_to_image, graph_to_dot, write_json,
+        Dep, DotOptions, Label, Node, TaskGraph,
+    };
+    use tempdir::TempDir;
+    use termcolor::Buffer;
+
+    #[test]
+    fn test_parse_config() {
+        let rc = Cursor::new(indoc! {r#"
+            # project defaults
+            format = "png"
+        "#});
+        let config = parse_config(rc).unwrap();
+        assert_eq!(config.format.as_deref(), Some("png"));
+        assert!(parse_config(Cursor::new("colour = \"red\"")).is_err());
+    }
+
+    #[test]
+    fn test_write_diagnostics() {
+        let warning = Diagnostic {
+            severity: "warning",
+            code: "undefined-dep",
+            message: "task `a` depends on `b`".to_string(),
+            task: Some("a".to_string()),
+            file: Some("Taskfile.yaml".to_string()),
+            line: Some(3),
+        };
+        let error = anyhow::anyhow!("no such file").context("lib.yaml");
+        let mut out = vec![];
+        write_diagnostics(&[warning], Some(&error), &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "severity": "warning",
+                    "code": "undefined-dep",
+                    "message": "task `a` depends on `b`",
+                    "file": "Taskfile.yaml",
+                    "line": 3,
+                    "task": "a",
+                },
+                {
+                    "severity": "error",
+                    "code": "error",
+                    "message": "lib.yaml: no such file",
+                    "file": null,
+                    "line": null,
+                    "task": null,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_format_precedence() {
+        let config = Settings {
+            format: Some("png".into()),
+        };
+        let none = Settings::default();
+        assert_eq!(resolve_format(None, None, &none).unwrap(), Format::Svg);
+        assert_eq!(resolve_format(None, None, &config).unwrap(), Format::Png);
+        assert_eq!(
+            resolve_format(None, Some("pdf"), &config).unwrap(),
+            Format::Pdf
+        );
+        assert_eq!(
+            resolve_format(Some(Format::Dot), Some("pdf"), &config).unwrap(),
+            Format::Dot
+        );
+        assert!(resolve_format(None, Some("gif"), &none).is_err());
+    }
+
+    #[test]
+    fn test_is_any_of() -> std::io::Result<()> {
+        let temp = TempDir::new("taskdep")?;
+        let input = temp.path().join("Taskfile.yaml");
+        File::create(&input)?;
+        let inputs = [canonicalize(&input)?];
+        assert!(is_any_of(&input, &inputs));
+        assert!(is_any_of(
+            &temp.path().join(".").join("Taskfile.yaml"),
+            &inputs
+        ));
+        assert!(!is_any_of(&temp.path().join("Taskfile.svg"), &inputs));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_task_list() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build, "lib:gen"]
+               build: {}
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let list = Cursor::new("# run in CI\nbuild\n\nrelease\n");
+        let roots = parse_task_list(list, &n, &g).unwrap();
+        assert_eq!(roots, vec![n["build"], n["release"]]);
+        // `lib:gen` is only a dependency, it isn't defined anywhere
+        assert!(parse_task_list(Cursor::new("lib:gen"), &n, &g).is_err());
+        assert!(parse_task_list(Cursor::new("deploy"), &n, &g).is_err());
+    }
+
+    #[test]
+    fn test_check_max_depth() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build]
+               build:
+                 deps: [compile]
+               compile: {}
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        assert!(check_max_depth(&g, 2).is_ok());
+        let err = check_max_depth(&g, 1).unwrap_err().to_string();
+        assert!(err.contains("release, build, compile"));
+        g.add_edge(n["release"], n["compile"], Dep::default());
+        let err = check_max_depth(&g, 10).unwrap_err().to_string();
+        assert!(err.contains("cycle: release, build, compile"));
+    }
+
+    #[test]
+    fn test_check_order() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 deps: [build, docs]
+               build:
+                 deps: [gen]
+               docs: {}
+               gen: {}
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let order = |names: &str| parse_task_list(Cursor::new(names), &n, &g);
+        let valid = order("docs\ngen\nbuild\nrelease").unwrap();
+        assert!(check_order(&g, &valid).is_ok());
+        let swapped = order("gen\nrelease\nbuild\ndocs").unwrap();
+        let err = check_order(&g, &swapped).unwrap_err().to_string();
+        assert_eq!(err, "task `release` comes before its dependency `build`");
+        let partial = order("gen\nbuild\nrelease").unwrap();
+        let err = check_order(&g, &partial).unwrap_err().to_string();
+        assert_eq!(err, "tasks missing from the order: docs");
+    }
+
+    #[test]
+    fn test_explain() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               release:
+                 desc: Publish a release
+                 deps: [build, docs]
+               build:
+                 deps: [gen]
+               gen:
+                 deps: [build]
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let mut out = vec![];
+        let tasks = TaskGraph::new(g);
+        explain(&tasks, "build", &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            indoc! {"
+                build
+                  depends on: gen
+                  dependencies in all, directly or not: 1
+                  in a cycle with: gen
+                  needed by: gen, release
+            "}
+        );
+        let mut out = vec![];
+        explain(&tasks, "release", &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("release\n  Publish a release\n"));
+        assert!(out.contains("  depends on: build, docs\n"));
+        assert!(out.contains("  dependencies in all, directly or not: 3\n"));
+        assert!(out.ends_with("  not in a cycle\n  needed by: nothing\n"));
+        let mut out = vec![];
+        explain(&tasks, "docs", &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("docs\n  not defined in any Taskfile\n"));
+    }
+
+    #[test]
+    fn test_print_cycles() {
+        let yaml = Cursor::new(indoc! {r#"
+             tasks:
+               a:
+                 deps: [b]
+               b:
+                 deps: [a, c]
+               c:
+                 deps: [a]
+               d: {}
+            "#});
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let mut plain = Buffer::no_color();
+        print_cycles(&g, &mut plain).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(plain.as_slice()),
+            "a -> b -> a (with c)\n"
+        );
+        let mut colored = Buffer::ansi();
+        print_cycles(&g, &mut colored).unwrap();
+        let colored = String::from_utf8_lossy(colored.as_slice()).into_owned();
+        assert!(colored.starts_with("\x1b["));
+        assert!(colored.contains("a -> b -> a\x1b[0m (with c)"));
+    }
+
+    #[test]
+    fn test_task_link() {
+        let mut n = Node::new("docs:build".into());
+        let template = "https://git.example.com/{file}#{task}";
+        assert_eq!(
+            task_link(template, &n),
+            "https://git.example.com/#docs:build"
+        );
+        n.file = Some("no/such/Taskfile.yaml".into());
+        assert_eq!(
+            task_link(template, &n),
+            "https://git.example.com/no/such/Taskfile.yaml#docs:build"
+        );
+        assert_eq!(task_link("\"{task}\"", &n), "%22docs:build%22");
+        assert_eq!(task_link("{task}:{line}", &n), "docs:build:");
+        n.line = Some(12);
+        assert_eq!(task_link("{task}:{line}", &n), "docs:build:12");
+        n.file = Some("no/such dir/#1 <100%>/Taskfile.yaml".into());
+        assert_eq!(
+            task_link("file://{file}#L{line}", &n),
+            "file://no/such%20dir/%231%20%3C100%25%3E/Taskfile.yaml#L12"
+        );
+        n.name = "say:\"hi\"".into();
+        assert_eq!(task_link("{task}", &n), "say:%22hi%22");
+    }
+
+    #[test]
+    fn test_page_file() {
+        assert_eq!(
+            page_file("out/Taskfile.svg", "", false),
+            Path::new("out/Taskfile.svg")
+        );
+        assert_eq!(
+            page_file("out/Taskfile.svg", "lib", false),
+            Path::new("out/Taskfile.lib.svg")
+        );
+        assert_eq!(page_file("graph", "lib", false), Path::new("graph.lib"));
+        assert_eq!(
+            page_file("out/index.svg", "lib", true),
+            Path::new("out/lib.svg")
+        );
+        let page = |namespace| {
+            (
+                page_file("out/index.svg", namespace, true),
+                format!("the page of namespace `{namespace}`"),
+            )
+        };
+        let output = (PathBuf::from("out/index.svg"), "the output".into());
+        assert!(check_distinct(&[output.clone(), page("lib")]).is_ok());
+        assert_eq!(
+            check_distinct(&[output, page("lib"), page("index")])
+                .unwrap_err()
+                .to_string(),
+            "the output and the page of namespace `index` would both be \
+             written to out/index.svg"
+        );
+    }
+
+    #[test]
+    fn test_input_roots() -> anyhow::Result<()> {
+        let temp = TempDir::new("taskdep")?;
+        let api = temp.path().join("api");
+        std::fs::create_dir(&api)?;
+        let mut f = File::create(api.join("Taskfile.yaml"))?;
+        f.write_all(
+            b"version: '3'\ntasks:\n  build:\n    deps: [gen]\n  gen: {}\n",
+        )?;
+        let ci = temp.path().join("ci.yaml");
+        File::create(&ci)?.write_all(b"version: '3'\ntasks:\n  build: {}\n")?;
+        let inputs = [api.join("Taskfile.yaml"), ci]
+            .map(|p| p.to_string_lossy().into_owned());
+        let roots = input_roots(&inputs, true);
+        assert_eq!(roots[0].1, ["api"]);
+        assert_eq!(roots[1].1, ["ci"]);
+        assert!(input_roots(&inputs, false)
+            .iter()
+            .all(|(_, ns)| ns.is_empty()));
+        let mut nodes = HashMap::new();
+        let mut graph = DiGraph::new();
+        for (path, namespace) in &roots {
+            build_graph_from(
+                &mut FileSystem::default(),
+                path,
+                namespace,
+                &mut nodes,
+                &mut graph,
+                true,
+                &HashMap::new(),
+            )?;
+        }
+        let mut names = nodes.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["api:build", "api:gen", "ci:build"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gvjson() {
+        let yaml = Cursor::new("tasks: {build: {deps: [gen]}}");
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(yaml, &[], &mut n, &mut g, true, &HashMap::new()).unwrap();
+        let dot = graph_to_dot(&g, &DotOptions::default());
+        let not_found = |e: &anyhow::Error| {
+            let e = e.root_cause().downcast_ref::<std::io::Error>();
+            e.is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+        };
+        let image = match dot_to_image(dot, Format::Gvjson.graphviz_name(), &[])
+        {
+            Ok(image) => image,
+            // Without Graphviz installed, as the JSON comes from it
+            Err(e) if not_found(&e) => return,
+            Err(e) => panic!("{e:?}"),
+        };
+        let json: serde_json::Value =
+            serde_json::from_slice(&image.stdout).unwrap();
+        assert_eq!(json["objects"].as_array().unwrap().len(), 2);
+        assert!(json["bb"].is_string());
+    }
+
+    #[test]
+    fn test_between() {
+        let between = "release..lib:gen".parse::<Between>().unwrap();
+        assert_eq!(
+            (between.from.as_str(), between.to.as_str()),
+            ("release", "lib:gen")
+        );
+        assert!("release".parse::<Between>().is_err());
+        assert!("release..".parse::<Between>().is_err());
+    }
+
+    #[test]
+    fn test_exit_status() {
+        let failed = || anyhow::anyhow!("failed");
+        assert_eq!(exit_status(&Ok(()), &[]), 0);
+        let findings = [Failure::Undefined, Failure::Cycles];
+        assert_eq!(exit_status(&Ok(()), &findings), 2);
+        assert_eq!(exit_status(&Ok(()), &[Failure::Undefined]), 3);
+        // An error has its own status, whatever the graph has
+        assert_eq!(exit_status(&Err(failed()), &findings), 1);
+        let parse = Failure::Parse.of(failed());
+        assert_eq!(parse.to_string(), "failed");
+        assert_eq!(exit_status(&Err(parse), &findings), 4);
+        let render = Failure::Render.of(failed()).context("rendering");
+        assert_eq!(exit_status(&Err(render), &[]), 5);
+        let both = Failure::Undefined.of(Failure::Cycles.of(failed()));
+        assert_eq!(exit_status(&Err(both), &[]), 2);
+    }
+
+    #[test]
+    fn test_dedup_deps() {
+        let mut g = DiGraph::new();
+        let a = g.add_node(Node::new("a".into()));
+        let b = g.add_node(Node::new("b".into()));
+        let silent = Dep {
+            silent: true,
+            ..Dep::default()
+        };
+        g.add_edge(a, b, silent);
+        g.add_edge(b, a, Dep::default());
+        g.add_edge(a, b, Dep::default());
+        dedup_deps(&mut g);
+        assert_eq!(g.edge_count(), 2);
+        let ab = g.find_edge(a, b).unwrap();
+        assert_eq!(g[ab], silent);
+        assert!(g.contains_edge(b, a));
+    }
+
+    #[test]
+    fn test_top_level() {
+        let yaml = indoc! {"
+            tasks:
+              release: {deps: [build]}
+              build: {}
+              a: {deps: [b]}
+              b: {deps: [a]}
+        "};
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(
+            Cursor::new(yaml),
+            &[],
+            &mut n,
+            &mut g,
+            true,
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(top_level(&g, None), [n["release"]]);
+        let entrypoints = [n["a"], n["build"]];
+        assert_eq!(top_level(&g, Some(&entrypoints)), entrypoints);
+    }
+
+    #[test]
+    fn test_redact() {
+        let yaml = indoc! {"
+            tasks:
+              build:
+                desc: Compile the secret project
+                summary: Uses the secret key
+                deps: [gen]
+              gen:
+                desc: Generate the secret code
+        "};
+        let mut n = HashMap::new();
+        let mut g = DiGraph::new();
+        build_graph(
+            Cursor::new(yaml),
+            &[],
+            &mut n,
+            &mut g,
+            true,
+            &HashMap::new(),
+        )
+        .unwrap();
+        redact(&mut g);
+        let dot = graph_to_dot(&g, &DotOptions::default());
+        let labeled = DotOptions {
+            label: Label::Desc,
+            ..DotOptions::default()
+        };
+        let desc = graph_to_dot(&g, &labeled);
+        let mut json = vec![];
+        write_json(&g, &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        for output in [&dot, &desc, &json] {
+            assert!(!output.contains("secret"), "{output}");
+        }
+        assert!(!dot.contains("tooltip"));
+        assert!(desc.contains(r#"label = "build""#));
+        assert!(json.contains(r#""build""#));
+    }
+
+    #[test]
+    fn test_remap() {
+        let idx = NodeIndex::new;
+        let moved = HashMap::from([(idx(0), idx(1)), (idx(2), idx(1))]);
+        let mut entrypoints = Some(vec![idx(2), idx(3), idx(0)]);
+        remap(&mut entrypoints, &moved);
+        assert_eq!(entrypoints, Some(vec![idx(1)]));
+        let mut entrypoints = None;
+        remap(&mut entrypoints, &moved);
+        assert_eq!(entrypoints, None);
+    }
+
+    #[test]
+    fn test_palette() {
+        let palette = "gold, #1f77b4,,/blues9/3".parse::<Palette>().unwrap();
+        assert_eq!(palette.0, ["gold", "#1f77b4", "/blues9/3"]);
+        assert!(" , ".parse::<Palette>().is_err());
+    }
+
+    #[test]
+    fn test_size() {
+        let size = "8,6".parse::<Size>().unwrap();
+        assert_eq!((size.width, size.height, size.fill), (8.0, 6.0, false));
+        assert_eq!(size.to_string(), "8,6");
+        assert_eq!("7.5, 10!".parse::<Size>().unwrap().to_string(), "7.5,10!");
+        assert_eq!("8x6".parse::<Size>().unwrap(), size);
+        for invalid in ["8", "8x6x2", "8,-6", "0,6", "8,6!!", "a,b"] {
+            assert!(invalid.parse::<Size>().is_err(), "{invalid}");
+        }
     }
 }
@@ -0,0 +1,172 @@
+use crate::escape_xml;
+use std::collections::HashSet;
+
+/// Height of the heading above each page
+const HEADING: f64 = 24.0;
+
+/// Space between a page and the separator line under it
+const GAP: f64 = 12.0;
+
+/// The id of the page of `namespace` in [`merge_svgs`], to link to it with
+/// `#ID`: `page` for the tasks outside of any namespace, and `page-NAMESPACE`
+/// for the others, with characters not allowed in ids replaced by `_`.
+pub fn page_id(namespace: &str) -> String {
+    if namespace.is_empty() {
+        return "page".to_string();
+    }
+    let namespace = namespace
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    format!("page-{namespace}")
+}
+
+/// The value of the attribute `name` in the tag `tag`.
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {name}=\"");
+    let start = tag.find(&pattern)? + pattern.len();
+    let end = tag[start..].find('"')?;
+    Some(&tag[start..start + end])
+}
+
+/// A length such as `62pt`, in points: Graphviz writes SVG sizes in points,
+/// and a unitless length is taken as pixels.
+fn points(length: &str) -> Option<f64> {
+    match length.strip_suffix("pt") {
+        Some(pt) => pt.parse().ok(),
+        None => length
+            .trim_end_matches("px")
+            .parse::<f64>()
+            .ok()
+            .map(|px| px * 0.75),
+    }
+}
+
+/// Prefix the ids of `body`, and the references to them, with `prefix`, so
+/// that they stay unique once the pages are in the same document. Other
+/// references, such as links to the other pages, are left alone.
+fn prefix_ids(body: &str, prefix: &str) -> String {
+    let ids = body
+        .match_indices(" id=\"")
+        .filter_map(|(i, m)| {
+            let start = i + m.len();
+            body[start..].find('"').map(|end| &body[start..start + end])
+        })
+        .collect::<HashSet<_>>();
+    let mut result = String::with_capacity(body.len());
+    let mut rest = body;
+    loop {
+        let next = [" id=\"", "href=\"#", "url(#"]
+            .into_iter()
+            .filter_map(|p| rest.find(p).map(|i| (i, p)))
+            .min();
+        let Some((i, pattern)) = next else {
+            break;
+        };
+        let start = i + pattern.len();
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(['"', ')']).unwrap_or(rest.len());
+        if ids.contains(&rest[..end]) {
+            result.push_str(prefix);
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Stack SVG pages, as pairs of a namespace and an SVG rendered by Graphviz,
+/// into a single SVG: each page is under a heading with its namespace, with
+/// a line separating it from the next, and has the id given by [`page_id`].
+pub fn merge_svgs(pages: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut body = String::new();
+    let (mut width, mut y) = (0.0f64, 0.0);
+    for (i, (namespace, svg)) in pages.iter().enumerate() {
+        let svg = String::from_utf8_lossy(svg);
+        let Some(start) = svg.find("<svg") else {
+            continue;
+        };
+        let Some(tag_end) = svg[start..].find('>').map(|j| start + j) else {
+            continue;
+        };
+        let tag = &svg[start..tag_end];
+        let end = svg.rfind("</svg>").unwrap_or(svg.len()).max(tag_end + 1);
+        let w = attr(tag, "width").and_then(points).unwrap_or_default();
+        let h = attr(tag, "height").and_then(points).unwrap_or_default();
+        let view_box = attr(tag, "viewBox")
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("0 0 {w} {h}"));
+        if i > 0 {
+            body.push_str(&format!(
+                "<line x1=\"0\" y1=\"{y}\" x2=\"100%\" y2=\"{y}\" stroke=\"#999\"/>\n"
+            ));
+        }
+        let heading = match namespace.as_str() {
+            "" => "(no namespace)".to_string(),
+            namespace => escape_xml(namespace),
+        };
+        body.push_str(&format!(
+            "<text x=\"4\" y=\"{}\" font-family=\"sans-serif\" font-size=\"14\" font-weight=\"bold\">{heading}</text>\n",
+            y + HEADING - 8.0
+        ));
+        body.push_str(&format!(
+            "<svg id=\"{}\" x=\"0\" y=\"{}\" width=\"{w}\" height=\"{h}\" viewBox=\"{view_box}\">",
+            page_id(namespace),
+            y + HEADING
+        ));
+        body.push_str(&prefix_ids(&svg[tag_end + 1..end], &format!("p{i}_")));
+        body.push_str("</svg>\n");
+        width = width.max(w);
+        y += HEADING + h + GAP;
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+         width=\"{width}pt\" height=\"{y}pt\" viewBox=\"0 0 {width} {y}\">\n\
+         {body}</svg>\n"
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::merge::{merge_svgs, page_id, points, prefix_ids};
+
+    #[test]
+    fn test_merge_svgs() {
+        assert_eq!(page_id(""), "page");
+        assert_eq!(page_id("lib:a b"), "page-lib_a_b");
+        assert_eq!(points("62pt"), Some(62.0));
+        assert_eq!(points("100"), Some(75.0));
+        assert_eq!(
+            prefix_ids(
+                r##"<g id="node1"><a xlink:href="#page-lib"/><use href="#node1" fill="url(#node1)"/></g>"##,
+                "p0_"
+            ),
+            r##"<g id="p0_node1"><a xlink:href="#page-lib"/><use href="#p0_node1" fill="url(#p0_node1)"/></g>"##
+        );
+        let page = |w, h| {
+            format!(
+                "<?xml?>\n<svg width=\"{w}pt\" height=\"{h}pt\" viewBox=\"0.00 0.00 {w}.00 {h}.00\">\n<g id=\"graph0\"/>\n</svg>\n"
+            )
+            .into_bytes()
+        };
+        let merged = merge_svgs(&[
+            ("".to_string(), page(50, 20)),
+            ("lib".to_string(), page(80, 40)),
+        ]);
+        let merged = String::from_utf8(merged).unwrap();
+        assert!(merged.contains("width=\"80pt\" height=\"132pt\""));
+        assert!(merged.contains(">(no namespace)</text>"));
+        assert!(merged.contains(
+            "<svg id=\"page-lib\" x=\"0\" y=\"80\" width=\"80\" height=\"40\""
+        ));
+        assert!(merged.contains("<g id=\"p0_graph0\"/>"));
+        assert!(merged.contains("<g id=\"p1_graph0\"/>"));
+        assert_eq!(merged.matches("<line ").count(), 1);
+        assert!(merged.ends_with("</svg>\n</svg>\n"));
+    }
+}
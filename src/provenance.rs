@@ -0,0 +1,65 @@
+use crate::escape_xml;
+use sha2::{Digest, Sha256};
+
+/// Namespace of the elements describing the Taskfiles an SVG was made from
+const NAMESPACE: &str = "https://github.com/waltermoreira/taskdep";
+
+/// Hex-encoded SHA-256 of `contents`.
+fn sha256(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Embed Taskfiles, as pairs of a path and contents, in the `<metadata>` of
+/// an SVG, so that it can be traced back to them once shared on its own.
+///
+/// Each file is a `taskdep:source` element with its `path` and `sha256`
+/// attributes, holding the contents verbatim.
+pub fn embed_sources(svg: &[u8], sources: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let svg = String::from_utf8_lossy(svg);
+    // Right after the opening tag, where metadata usually goes
+    let Some(start) = svg
+        .find("<svg")
+        .and_then(|i| svg[i..].find('>').map(|j| i + j + 1))
+    else {
+        return svg.into_owned().into_bytes();
+    };
+    let mut metadata = format!("\n<metadata xmlns:taskdep=\"{NAMESPACE}\">\n");
+    for (path, contents) in sources {
+        let text = String::from_utf8_lossy(contents);
+        metadata.push_str(&format!(
+            "<taskdep:source path=\"{}\" sha256=\"{}\"><![CDATA[{}]]></taskdep:source>\n",
+            escape_xml(path),
+            sha256(contents),
+            text.replace("]]>", "]]]]><![CDATA[>")
+        ));
+    }
+    metadata.push_str("</metadata>");
+    format!("{}{metadata}{}", &svg[..start], &svg[start..]).into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::provenance::{embed_sources, sha256};
+
+    #[test]
+    fn test_embed_sources() {
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let sources = [("a&b.yaml".to_string(), b"tasks: {x: ']]>'}".to_vec())];
+        let svg = embed_sources(
+            b"<?xml?>\n<svg width=\"1\">\n<g/>\n</svg>\n",
+            &sources,
+        );
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<?xml?>\n<svg width=\"1\">\n<metadata "));
+        assert!(svg.contains("path=\"a&amp;b.yaml\" sha256=\""));
+        assert!(svg.contains("<![CDATA[tasks: {x: ']]]]><![CDATA[>'}]]>"));
+        assert!(svg.ends_with("</metadata>\n<g/>\n</svg>\n"));
+        assert_eq!(embed_sources(b"not svg", &sources), b"not svg");
+    }
+}
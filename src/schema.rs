@@ -0,0 +1,136 @@
+use crate::source::Source;
+use crate::InputFormat;
+use anyhow::{anyhow, bail, Result};
+use jsonschema::Validator;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// A JSON Schema for Task v3 Taskfiles, with the keys and shapes documented
+/// by Task. It is much stricter than [`crate::parse`], which ignores the keys
+/// it doesn't use.
+const SCHEMA: &str = include_str!("taskfile.schema.json");
+
+fn validator() -> &'static Validator {
+    static VALIDATOR: OnceLock<Validator> = OnceLock::new();
+    VALIDATOR.get_or_init(|| {
+        let schema = serde_json::from_str(SCHEMA).expect("bundled schema");
+        jsonschema::validator_for(&schema).expect("bundled schema")
+    })
+}
+
+/// Check every YAML document in `f` against the Taskfile schema, returning
+/// the violations as `PATH: MESSAGE`, with JSON pointers as paths.
+pub fn validate<R>(f: R) -> Result<Vec<String>>
+where
+    R: Read,
+{
+    let mut violations = vec![];
+    for document in serde_yaml::Deserializer::from_reader(f) {
+        let mut value = Value::deserialize(document)?;
+        value.apply_merge()?;
+        let json = serde_json::to_value(&value)
+            .map_err(|e| anyhow!("can't check against the schema: {e}"))?;
+        for error in validator().iter_errors(&json) {
+            let path = match error.instance_path().as_str() {
+                "" => "/",
+                path => path,
+            };
+            violations.push(format!("{path}: {error}"));
+        }
+    }
+    Ok(violations)
+}
+
+/// A [`Source`] checking each Taskfile it opens against the Taskfile schema,
+/// and failing on the first one that doesn't match.
+pub struct Validating<S> {
+    source: S,
+}
+
+impl<S> Validating<S> {
+    pub fn new(source: S) -> Self {
+        Validating { source }
+    }
+}
+
+impl<S> Source for Validating<S>
+where
+    S: Source,
+{
+    fn open(&mut self, path: &str) -> Result<Box<dyn Read>> {
+        let mut contents = vec![];
+        self.source.open(path)?.read_to_end(&mut contents)?;
+        let violations = validate(contents.as_slice())?;
+        if !violations.is_empty() {
+            bail!(
+                "{path}: doesn't match the Taskfile schema\n  {}",
+                violations.join("\n  ")
+            );
+        }
+        Ok(Box::new(Cursor::new(contents)))
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.source.files()
+    }
+
+    fn format(&self, path: &str) -> InputFormat {
+        self.source.format(path)
+    }
+
+    fn canonical(&self, path: &str) -> PathBuf {
+        self.source.canonical(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::validate;
+    use indoc::indoc;
+
+    #[test]
+    fn test_validate() {
+        let valid = indoc! {r#"
+            version: '3'
+            includes:
+              lib: ./lib.yaml
+            tasks:
+              build:
+                deps: [gen, {task: lint, vars: {STRICT: true}}]
+                cmds:
+                  - go build
+              gen: go generate
+            "#};
+        assert!(validate(valid.as_bytes()).unwrap().is_empty());
+        let invalid = indoc! {r#"
+            version: '3'
+            tasks:
+              build:
+                dep: [gen]
+                deps: gen
+            "#};
+        let violations = validate(invalid.as_bytes()).unwrap();
+        assert_eq!(violations.len(), 2);
+        assert!(violations
+            .iter()
+            .any(|v| v.starts_with("/tasks/build/deps: ")));
+        assert!(violations
+            .iter()
+            .any(|v| v.starts_with("/tasks/build: ") && v.contains("'dep'")));
+        let anchors = indoc! {r#"
+            version: '3'
+            x-go: &go
+              cmds: [go build]
+            tasks:
+              build:
+                <<: *go
+            "#};
+        assert!(validate(anchors.as_bytes()).unwrap().is_empty());
+        let missing_version = "tasks: {}";
+        let violations = validate(missing_version.as_bytes()).unwrap();
+        assert!(violations[0].starts_with("/: "));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::escape_xml;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+/// How often the page checks for a new image, in milliseconds
+const POLL: u32 = 1000;
+
+/// The page served at `/`: it shows `/image.svg` as an `<object>`, so that
+/// its links and scripts still work, and reloads it when `/version` changes.
+fn page(title: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+</head>
+<body style="margin: 0">
+<object id="image" data="/image.svg" type="image/svg+xml"></object>
+<script>
+let version = null;
+setInterval(async () => {{
+  try {{
+    const latest = await (await fetch("/version")).text();
+    if (version !== null && latest !== version) {{
+      document.getElementById("image").data = "/image.svg?" + latest;
+    }}
+    version = latest;
+  }} catch (e) {{
+    // Stopped, or restarting: try again later
+  }}
+}}, {POLL});
+</script>
+</body>
+</html>
+"#,
+        escape_xml(title)
+    )
+}
+
+/// The version of `image`: when it was last written, in nanoseconds since
+/// the epoch, or 0 when it isn't written yet.
+fn version(image: &Path) -> u128 {
+    fs::metadata(image)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+}
+
+fn respond(stream: TcpStream, image: &Path) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers say nothing that matters here
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    let title = image.file_name().unwrap_or_default().to_string_lossy();
+    let (status, content_type, body) = match path {
+        "/" => ("200 OK", "text/html", page(&title).into_bytes()),
+        "/version" => (
+            "200 OK",
+            "text/plain",
+            version(image).to_string().into_bytes(),
+        ),
+        "/image.svg" => match fs::read(image) {
+            Ok(svg) => ("200 OK", "image/svg+xml", svg),
+            Err(e) => ("404 Not Found", "text/plain", e.to_string().into()),
+        },
+        _ => ("404 Not Found", "text/plain", b"not found".to_vec()),
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)
+}
+
+/// Serve the SVG `image` on `listener` in the background, with a page at
+/// `/` that shows it and reloads it whenever it's written anew.
+pub fn serve(listener: TcpListener, image: PathBuf) {
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let image = image.clone();
+            thread::spawn(move || {
+                // A browser going away mid-response is no concern of ours
+                let _ = respond(stream, &image);
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::serve::serve;
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use tempdir::TempDir;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve() {
+        let dir = TempDir::new("taskdep").unwrap();
+        let image = dir.path().join("Taskfile.svg");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        serve(listener, image.clone());
+        assert!(get(addr, "/image.svg").starts_with("HTTP/1.1 404"));
+        assert!(get(addr, "/version").ends_with("\r\n\r\n0"));
+        File::create(&image).unwrap().write_all(b"<svg/>").unwrap();
+        let response = get(addr, "/image.svg?1");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: image/svg+xml\r\n"));
+        assert!(response.ends_with("\r\n\r\n<svg/>"));
+        assert!(!get(addr, "/version").ends_with("\r\n\r\n0"));
+        let page = get(addr, "/");
+        assert!(page.contains("<title>Taskfile.svg</title>"));
+        assert!(page.contains("data=\"/image.svg\""));
+        assert!(get(addr, "/other").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::InputFormat;
+use anyhow::{anyhow, bail, Result};
+use std::fs::{canonicalize, File};
+use std::io::{Cursor, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use zip::ZipArchive;
+
+/// Where Taskfiles, and the Taskfiles they include, are read from
+pub trait Source {
+    fn open(&mut self, path: &str) -> Result<Box<dyn Read>>;
+
+    /// Canonical paths of the files on disk opened so far
+    fn files(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// How to parse the Taskfile at `path`
+    fn format(&self, path: &str) -> InputFormat {
+        InputFormat::Auto.resolve(path)
+    }
+
+    /// A path that is the same for all the paths to the Taskfile at `path`,
+    /// such as through symlinks, to tell when an include comes back to it
+    fn canonical(&self, path: &str) -> PathBuf {
+        PathBuf::from(path)
+    }
+}
+
+impl<S> Source for Box<S>
+where
+    S: Source + ?Sized,
+{
+    fn open(&mut self, path: &str) -> Result<Box<dyn Read>> {
+        (**self).open(path)
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        (**self).files()
+    }
+
+    fn format(&self, path: &str) -> InputFormat {
+        (**self).format(path)
+    }
+
+    fn canonical(&self, path: &str) -> PathBuf {
+        (**self).canonical(path)
+    }
+}
+
+/// Another [`Source`], parsing the Taskfile at `path` in the given format,
+/// whatever its extension.
+pub struct WithFormat<S> {
+    source: S,
+    path: String,
+    format: InputFormat,
+}
+
+impl<S> WithFormat<S> {
+    pub fn new(source: S, path: &str, format: InputFormat) -> Self {
+        WithFormat {
+            source,
+            path: path.to_string(),
+            format,
+        }
+    }
+}
+
+impl<S> Source for WithFormat<S>
+where
+    S: Source,
+{
+    fn open(&mut self, path: &str) -> Result<Box<dyn Read>> {
+        self.source.open(path)
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.source.files()
+    }
+
+    fn format(&self, path: &str) -> InputFormat {
+        if path == self.path {
+            self.format.resolve(path)
+        } else {
+            self.source.format(path)
+        }
+    }
+
+    fn canonical(&self, path: &str) -> PathBuf {
+        self.source.canonical(path)
+    }
+}
+
+/// Taskfiles on disk, with relative paths taken from the current directory,
+/// and `-` for the standard input
+#[derive(Debug, Default)]
+pub struct FileSystem {
+    opened: Vec<PathBuf>,
+    /// The standard input, once read, for `-` to be opened again
+    stdin: Option<Vec<u8>>,
+}
+
+impl Source for FileSystem {
+    fn open(&mut self, path: &str) -> Result<Box<dyn Read>> {
+        if path == "-" {
+            if self.stdin.is_none() {
+                let mut stdin = vec![];
+                std::io::stdin().read_to_end(&mut stdin)?;
+                self.stdin = Some(stdin);
+            }
+            return Ok(Box::new(Cursor::new(self.stdin.clone().unwrap())));
+        }
+        let f = File::open(path).map_err(|e| anyhow!("{path}: {e}"))?;
+        self.opened.extend(canonicalize(path));
+        Ok(Box::new(f))
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.opened.clone()
+    }
+
+    fn canonical(&self, path: &str) -> PathBuf {
+        canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+    }
+}
+
+/// Taskfiles inside a zip archive
+///
+/// Paths are taken from the root of the archive, and can't point outside of
+/// it.
+pub struct ZipSource<R> {
+    archive: ZipArchive<R>,
+}
+
+impl<R> ZipSource<R>
+where
+    R: Read + Seek,
+{
+    pub fn new(reader: R) -> Result<Self> {
+        Ok(ZipSource {
+            archive: ZipArchive::new(reader)?,
+        })
+    }
+}
+
+/// Normalize `path` as a path from the root of an archive.
+fn archive_path(path: &str) -> Result<String> {
+    let mut result = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(c) => result.push(c),
+            Component::CurDir => (),
+            Component::ParentDir if result.pop() => (),
+            _ => bail!("{path}: outside of the archive"),
+        }
+    }
+    Ok(result.to_string_lossy().replace('\\', "/"))
+}
+
+impl<R> Source for ZipSource<R>
+where
+    R: Read + Seek,
+{
+    fn open(&mut self, path: &str) -> Result<Box<dyn Read>> {
+        let name = archive_path(path)?;
+        let mut entry = self
+            .archive
+            .by_name(&name)
+            .map_err(|e| anyhow!("{path}: {e}"))?;
+        let mut contents = vec![];
+        entry.read_to_end(&mut contents)?;
+        Ok(Box::new(Cursor::new(contents)))
+    }
+
+    fn canonical(&self, path: &str) -> PathBuf {
+        PathBuf::from(archive_path(path).unwrap_or_else(|_| path.to_string()))
+    }
+}
+
+/// Names of the Taskfile of a directory, in the order Task looks for them
+const TASKFILE_NAMES: [&str; 4] = [
+    "Taskfile.yml",
+    "taskfile.yml",
+    "Taskfile.yaml",
+    "taskfile.yaml",
+];
+
+/// The Taskfiles in `dir` and its subdirectories, each with the namespace of
+/// its directory relative to `dir`: `["services", "api"]` for
+/// `services/api/Taskfile.yml`, and none for the Taskfile of `dir` itself,
+/// which comes first.
+///
+/// Hidden directories are skipped. When a directory has several Taskfiles,
+/// only the one Task would read is kept.
+pub fn find_taskfiles(dir: &Path) -> Result<Vec<(PathBuf, Vec<String>)>> {
+    let mut found = vec![];
+    find_taskfiles_in(dir, &mut vec![], &mut found)?;
+    Ok(found)
+}
+
+fn find_taskfiles_in(
+    dir: &Path,
+    namespace: &mut Vec<String>,
+    found: &mut Vec<(PathBuf, Vec<String>)>,
+) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .map_err(|e| anyhow!("{}: {e}", dir.display()))?
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    let taskfile = TASKFILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file());
+    found.extend(taskfile.map(|path| (path, namespace.clone())));
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        namespace.push(name);
+        find_taskfiles_in(&entry.path(), namespace, found)?;
+        namespace.pop();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::source::{archive_path, find_taskfiles, FileSystem, Source};
+    use std::fs::{create_dir_all, File};
+    use std::io::Read;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_archive_path() {
+        assert_eq!(archive_path("./a/b.yaml").unwrap(), "a/b.yaml");
+        assert_eq!(archive_path("a/../b.yaml").unwrap(), "b.yaml");
+        assert!(archive_path("../b.yaml").is_err());
+        assert!(archive_path("/etc/b.yaml").is_err());
+    }
+
+    #[test]
+    fn test_stdin_opened_again() {
+        let mut source = FileSystem {
+            stdin: Some(b"tasks: {}\n".to_vec()),
+            ..Default::default()
+        };
+        for _ in 0..2 {
+            let mut contents = String::new();
+            source
+                .open("-")
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            assert_eq!(contents, "tasks: {}\n");
+        }
+    }
+
+    #[test]
+    fn test_find_taskfiles() {
+        let tmp = TempDir::new("taskdep").unwrap();
+        let root = tmp.path();
+        for dir in ["services/api", "services/web", ".git/hooks", "docs"] {
+            create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "Taskfile.yaml",
+            "services/api/Taskfile.yml",
+            "services/api/Taskfile.yaml",
+            "services/web/taskfile.yaml",
+            ".git/hooks/Taskfile.yml",
+        ] {
+            File::create(root.join(file)).unwrap();
+        }
+        let found = find_taskfiles(root)
+            .unwrap()
+            .into_iter()
+            .map(|(path, ns)| {
+                let path = path.strip_prefix(root).unwrap().to_path_buf();
+                (path.to_string_lossy().into_owned(), ns.join(":"))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("Taskfile.yaml".to_string(), "".to_string()),
+                ("services/api/Taskfile.yml".into(), "services:api".into()),
+                ("services/web/taskfile.yaml".into(), "services:web".into()),
+            ]
+        );
+    }
+}